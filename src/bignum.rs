@@ -0,0 +1,321 @@
+use num_bigint::RandBigInt;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::error::Error;
+
+pub use num_bigint::{BigInt, BigUint, Sign};
+
+
+// Window width of the fixed window exponentiation, 16 precomputed powers of the base.
+const WINDOW_BITS: u64 = 4;
+
+// Montgomery arithmetic modulo an odd n with R = 2^k the smallest power of 2^64 above n. Products are reduced with
+// shifts and masks instead of divisions by n, which is what makes repeated multiplication in modexp cheap.
+// Setting up the context costs about half an exponentiation, so code that exponentiates repeatedly modulo the same
+// n (DH groups, RSA primes, Miller-Rabin rounds) builds it once and calls pow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Montgomery {
+    modulus: BigUint,
+    r_bits: u64,
+    mask: BigUint,
+    // -n^-1 mod R
+    n_prime: BigUint,
+    // R^2 mod n, used to move values into Montgomery form
+    r_squared: BigUint,
+}
+
+impl Montgomery {
+    pub fn new(modulus: &BigUint) -> Result<Montgomery, Error> {
+        if modulus.is_even() || modulus.is_one() {
+            return Err(Error::InvalidModulus("montgomery arithmetic needs an odd modulus above 1".to_owned()));
+        }
+
+        let r_bits = modulus.bits().div_ceil(64) * 64;
+        let r = BigUint::one() << r_bits;
+        let mask = &r - 1u32;
+        let n_inverse = modinv(modulus, &r).unwrap();
+        let n_prime = &r - n_inverse;
+        let r_squared = (&r * &r) % modulus;
+
+        Ok(Montgomery { modulus: modulus.clone(), r_bits, mask, n_prime, r_squared })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    // REDC: t * R^-1 mod n for t < n * R.
+    fn reduce(&self, t: &BigUint) -> BigUint {
+        let m = ((t & &self.mask) * &self.n_prime) & &self.mask;
+        let u = (t + m * &self.modulus) >> self.r_bits;
+        if u >= self.modulus { u - &self.modulus } else { u }
+    }
+
+    pub fn to_montgomery(&self, a: &BigUint) -> BigUint {
+        self.reduce(&((a % &self.modulus) * &self.r_squared))
+    }
+
+    pub fn to_standard(&self, a: &BigUint) -> BigUint {
+        self.reduce(a)
+    }
+
+    // Product of two values in Montgomery form, itself in Montgomery form.
+    pub fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        self.reduce(&(a * b))
+    }
+
+    // Fixed window exponentiation, scanning the exponent WINDOW_BITS at a time from the top.
+    pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        let base = self.to_montgomery(base);
+        let mut table = vec![self.to_montgomery(&BigUint::one())];
+        for i in 1..1usize << WINDOW_BITS {
+            table.push(self.mul(&table[i - 1], &base));
+        }
+
+        let mut result = table[0].clone();
+        let windows = exponent.bits().div_ceil(WINDOW_BITS);
+        for window in (0..windows).rev() {
+            for _ in 0..WINDOW_BITS {
+                result = self.mul(&result, &result);
+            }
+            let digit = (0..WINDOW_BITS).fold(0usize, |digit, bit| {
+                digit | (exponent.bit(window * WINDOW_BITS + bit) as usize) << bit
+            });
+            if digit != 0 {
+                result = self.mul(&result, &table[digit]);
+            }
+        }
+
+        self.to_standard(&result)
+    }
+}
+
+// base^exponent mod modulus, through Montgomery multiplication when the modulus is odd (every prime modulus
+// used by DH and RSA) and plain square and multiply otherwise. Builds a fresh Montgomery context on every call.
+pub fn modexp(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    assert!(!modulus.is_zero(), "modexp with a zero modulus");
+
+    match Montgomery::new(modulus) {
+        Ok(montgomery) => montgomery.pow(base, exponent),
+        Err(_) => base.modpow(exponent, modulus),
+    }
+}
+
+// Returns (g, x, y) with a * x + b * y = g = gcd(a, b) and g non-negative.
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        (old_r, r) = (r.clone(), old_r - &quotient * r);
+        (old_x, x) = (x.clone(), old_x - &quotient * x);
+        (old_y, y) = (y.clone(), old_y - &quotient * y);
+    }
+
+    if old_r.sign() == Sign::Minus {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// Inverse of a modulo m, None when a and m are not coprime.
+pub fn modinv(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+
+    let m_signed = BigInt::from(m.clone());
+    let (g, x, _) = extended_gcd(&BigInt::from(a % m), &m_signed);
+    if !g.is_one() {
+        return None;
+    }
+    x.mod_floor(&m_signed).to_biguint()
+}
+
+// Largest r with r^n <= a.
+pub fn nth_root(a: &BigUint, n: u32) -> BigUint {
+    a.nth_root(n)
+}
+
+// The unique x below the product of the moduli with x = residue mod modulus for every pair, together with that
+// product. The moduli must be pairwise coprime.
+pub fn crt(congruences: &[(BigUint, BigUint)]) -> Result<(BigUint, BigUint), Error> {
+    let mut result = BigUint::zero();
+    let mut product = BigUint::one();
+
+    for (residue, modulus) in congruences {
+        if modulus.is_zero() {
+            return Err(Error::InvalidModulus("crt modulus must be non-zero".to_owned()));
+        }
+        // lift the running solution: result + product * t = residue (mod modulus)
+        let inverse = modinv(&product, modulus)
+            .ok_or_else(|| Error::InvalidModulus("crt moduli must be pairwise coprime".to_owned()))?;
+        let difference = (residue + modulus - &result % modulus) % modulus;
+        let t = (difference * inverse) % modulus;
+        result += &product * t;
+        product *= modulus;
+    }
+
+    Ok((result, product))
+}
+
+// Uniformly random integer in [0, bound).
+pub fn random_below(bound: &BigUint) -> BigUint {
+    rand::thread_rng().gen_biguint_below(bound)
+}
+
+// Uniformly random integer in [low, high).
+pub fn random_range(low: &BigUint, high: &BigUint) -> BigUint {
+    rand::thread_rng().gen_biguint_range(low, high)
+}
+
+pub fn from_bytes_be(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+pub fn from_bytes_le(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_le(bytes)
+}
+
+fn fixed_length_error(n: &BigUint, len: usize) -> Error {
+    Error::InvalidFormat(format!("{} bit integer does not fit in {} bytes", n.bits(), len))
+}
+
+// Big endian bytes left padded with zeros to exactly len bytes, as RSA and DH encode their integers.
+pub fn to_bytes_be(n: &BigUint, len: usize) -> Result<Vec<u8>, Error> {
+    let bytes = if n.is_zero() { Vec::new() } else { n.to_bytes_be() };
+    if bytes.len() > len {
+        return Err(fixed_length_error(n, len));
+    }
+    Ok([vec![0u8; len - bytes.len()], bytes].concat())
+}
+
+pub fn to_bytes_le(n: &BigUint, len: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = if n.is_zero() { Vec::new() } else { n.to_bytes_le() };
+    if bytes.len() > len {
+        return Err(fixed_length_error(n, len));
+    }
+    bytes.resize(len, 0);
+    Ok(bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_modexp() {
+        assert_eq!(BigUint::from(445u32), modexp(&BigUint::from(4u32), &BigUint::from(13u32), &BigUint::from(497u32)));
+        // even modulus takes the plain path
+        assert_eq!(BigUint::from(21u32), modexp(&BigUint::from(3u32), &BigUint::from(5u32), &BigUint::from(74u32)));
+        assert_eq!(BigUint::one(), modexp(&BigUint::from(7u32), &BigUint::zero(), &BigUint::from(13u32)));
+        assert_eq!(BigUint::zero(), modexp(&BigUint::from(7u32), &BigUint::from(3u32), &BigUint::one()));
+
+        for bits in [64, 521, 1024] {
+            let modulus = random_below(&(BigUint::one() << bits)) | BigUint::one();
+            let base = random_below(&(BigUint::one() << (bits + 10)));
+            let exponent = random_below(&(BigUint::one() << bits));
+            assert_eq!(base.modpow(&exponent, &modulus), modexp(&base, &exponent, &modulus));
+        }
+    }
+
+    #[test]
+    fn test_montgomery() {
+        assert!(Montgomery::new(&BigUint::from(10u32)).is_err());
+        assert!(Montgomery::new(&BigUint::one()).is_err());
+
+        let modulus = BigUint::from(0xffff_ffff_ffff_ffc5u64);
+        let montgomery = Montgomery::new(&modulus).unwrap();
+        assert_eq!(&modulus, montgomery.modulus());
+
+        let a = BigUint::from(0x1234_5678_9abc_def0u64);
+        let b = BigUint::from(0x0fed_cba9_8765_4321u64);
+        let product = montgomery.mul(&montgomery.to_montgomery(&a), &montgomery.to_montgomery(&b));
+        assert_eq!((&a * &b) % &modulus, montgomery.to_standard(&product));
+    }
+
+    // cargo test --release bench_modexp -- --ignored --nocapture
+    #[test]
+    #[ignore = "benchmark, run in release mode"]
+    fn bench_modexp() {
+        let iterations = 20;
+        for bits in [512, 1024, 2048] {
+            let modulus = random_below(&(BigUint::one() << bits)) | (BigUint::one() << (bits - 1)) | BigUint::one();
+            let base = random_below(&modulus);
+            let exponent = random_below(&modulus);
+            let montgomery = Montgomery::new(&modulus).unwrap();
+
+            let time = |f: &dyn Fn() -> BigUint| {
+                let start = std::time::Instant::now();
+                for _ in 0..iterations {
+                    std::hint::black_box(f());
+                }
+                start.elapsed() / iterations
+            };
+            let context = time(&|| Montgomery::new(&modulus).unwrap().modulus().clone());
+            let uncached = time(&|| modexp(&base, &exponent, &modulus));
+            let cached = time(&|| montgomery.pow(&base, &exponent));
+            let modpow = time(&|| base.modpow(&exponent, &modulus));
+
+            println!("{} bits: context {:?}, modexp {:?}, cached pow {:?}, BigUint::modpow {:?}", bits, context, uncached, cached, modpow);
+        }
+    }
+
+    #[test]
+    fn test_extended_gcd_and_modinv() {
+        let (g, x, y) = extended_gcd(&BigInt::from(240), &BigInt::from(46));
+        assert_eq!(BigInt::from(2), g);
+        assert_eq!(g, BigInt::from(240) * x + BigInt::from(46) * y);
+
+        assert_eq!(Some(BigUint::from(2753u32)), modinv(&BigUint::from(17u32), &BigUint::from(3120u32)));
+        assert_eq!(None, modinv(&BigUint::from(6u32), &BigUint::from(9u32)));
+        assert_eq!(None, modinv(&BigUint::from(6u32), &BigUint::zero()));
+    }
+
+    #[test]
+    fn test_nth_root() {
+        let cube = BigUint::from(12345678901234567890u64).pow(3);
+        assert_eq!(BigUint::from(12345678901234567890u64), nth_root(&cube, 3));
+        assert_eq!(BigUint::from(12345678901234567889u64), nth_root(&(cube - 1u32), 3));
+    }
+
+    #[test]
+    fn test_crt() {
+        let congruences = [(2u32, 3u32), (3, 5), (2, 7)].map(|(r, m)| (BigUint::from(r), BigUint::from(m)));
+        assert_eq!(Ok((BigUint::from(23u32), BigUint::from(105u32))), crt(&congruences));
+
+        let shared_factor = [(1u32, 4u32), (1, 6)].map(|(r, m)| (BigUint::from(r), BigUint::from(m)));
+        assert!(crt(&shared_factor).is_err());
+    }
+
+    #[test]
+    fn test_random_range() {
+        let low = BigUint::from(1000u32);
+        let high = BigUint::from(1010u32);
+        for _ in 0..100 {
+            let n = random_range(&low, &high);
+            assert!(n >= low && n < high);
+            assert!(random_below(&high) < high);
+        }
+    }
+
+    #[test]
+    fn test_byte_conversion() {
+        let n = from_bytes_be(&[0x01, 0x02, 0x03]);
+        assert_eq!(BigUint::from(0x010203u32), n);
+        assert_eq!(n, from_bytes_le(&[0x03, 0x02, 0x01]));
+
+        assert_eq!(Ok(vec![0, 0, 1, 2, 3]), to_bytes_be(&n, 5));
+        assert_eq!(Ok(vec![3, 2, 1, 0, 0]), to_bytes_le(&n, 5));
+        assert_eq!(Ok(vec![0, 0]), to_bytes_be(&BigUint::zero(), 2));
+        assert!(to_bytes_be(&n, 2).is_err());
+        assert!(to_bytes_le(&n, 2).is_err());
+    }
+
+}
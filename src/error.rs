@@ -0,0 +1,126 @@
+use std::fmt;
+
+
+// Crate wide error type returned by the fallible helpers instead of panicking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // A character outside of the expected alphabet, at the given byte offset of the input.
+    InvalidCharacter { character: char, position: usize },
+    // Hex input must contain an even number of digits.
+    OddLength { length: usize },
+    // Base64 input with missing, misplaced or excess '=' padding, at the given byte offset of the input.
+    InvalidPadding { position: usize },
+    // Block cipher keys must have one of the lengths supported by the cipher.
+    InvalidKeyLength { length: usize },
+    // Input to a block cipher without padding must be a whole number of blocks.
+    InvalidBlockLength { length: usize, block_size: usize },
+    // Initialisation vectors and counter blocks must be exactly one block long.
+    InvalidIvLength { length: usize, block_size: usize },
+    // A block cipher whose block size the mode of operation cannot use.
+    UnsupportedBlockSize { block_size: usize },
+    // Nonces must have the length required by the counter layout.
+    InvalidNonceLength { length: usize, expected: usize },
+    // Decrypted plaintext whose padding does not validate.
+    Padding(PaddingError),
+    // A range of bytes that does not fit inside the buffer it addresses.
+    OutOfBounds { offset: usize, length: usize, size: usize },
+    // Two inputs that must be the same length, such as known and replacement plaintext.
+    LengthMismatch { expected: usize, actual: usize },
+    // Decrypted plaintext with bytes above 0x7f, the receiver echoes the plaintext back in the error.
+    NonAsciiPlaintext { plaintext: Vec<u8> },
+    // A modulus the modular arithmetic cannot work with, or moduli that are not pairwise coprime.
+    InvalidModulus(String),
+    // Reading or writing a file failed, with the underlying io error message.
+    Io(String),
+    // A message the service has already processed once.
+    Replayed,
+    // Structured input that does not follow the expected format.
+    InvalidFormat(String),
+    // An attack could not complete against the given oracle.
+    AttackFailed(String),
+}
+
+// Padding validation failures, kept separate so callers (and padding oracles) can tell them apart from other errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    // Padded input must be a non-empty whole number of blocks.
+    InvalidLength { length: usize, block_size: usize },
+    // The padding length byte is zero or larger than the block size.
+    PaddingLengthOutOfRange { value: u8 },
+    // The padding bytes do not match the scheme.
+    MismatchedPaddingBytes,
+    // The padding length has to fit in a single non-zero byte.
+    UnsupportedBlockSize { block_size: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidCharacter { character, position } =>
+                write!(f, "invalid character {:?} at position {}", character, position),
+            Error::OddLength { length } =>
+                write!(f, "odd number of digits: {}", length),
+            Error::InvalidPadding { position } =>
+                write!(f, "invalid padding at position {}", position),
+            Error::InvalidKeyLength { length } =>
+                write!(f, "invalid key length: {}", length),
+            Error::InvalidBlockLength { length, block_size } =>
+                write!(f, "length {} is not a multiple of the block size {}", length, block_size),
+            Error::InvalidIvLength { length, block_size } =>
+                write!(f, "iv length {} does not match the block size {}", length, block_size),
+            Error::UnsupportedBlockSize { block_size } =>
+                write!(f, "unsupported block size: {}", block_size),
+            Error::InvalidNonceLength { length, expected } =>
+                write!(f, "nonce length {} does not match the expected {}", length, expected),
+            Error::Padding(e) =>
+                write!(f, "{}", e),
+            Error::OutOfBounds { offset, length, size } =>
+                write!(f, "range of {} bytes at offset {} does not fit in {} bytes", length, offset, size),
+            Error::LengthMismatch { expected, actual } =>
+                write!(f, "expected {} bytes, got {}", expected, actual),
+            Error::NonAsciiPlaintext { plaintext } =>
+                write!(f, "plaintext is not ascii: {}", hex::encode(plaintext)),
+            Error::InvalidModulus(reason) =>
+                write!(f, "invalid modulus: {}", reason),
+            Error::Io(reason) =>
+                write!(f, "io error: {}", reason),
+            Error::Replayed =>
+                write!(f, "message has already been processed"),
+            Error::InvalidFormat(reason) =>
+                write!(f, "invalid format: {}", reason),
+            Error::AttackFailed(reason) =>
+                write!(f, "attack failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+impl From<PaddingError> for Error {
+    fn from(e: PaddingError) -> Self {
+        Error::Padding(e)
+    }
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::InvalidLength { length, block_size } =>
+                write!(f, "padded length {} is not a whole number of {} byte blocks", length, block_size),
+            PaddingError::PaddingLengthOutOfRange { value } =>
+                write!(f, "invalid padding length byte: {}", value),
+            PaddingError::MismatchedPaddingBytes =>
+                write!(f, "invalid padding bytes"),
+            PaddingError::UnsupportedBlockSize { block_size } =>
+                write!(f, "unsupported padding block size: {}", block_size),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
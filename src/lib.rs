@@ -1,10 +1,11 @@
-mod set1;
-mod set2;
-mod set3;
-mod set4;
-mod set5;
-mod set6;
-mod set7;
-mod set8;
-
+pub mod bignum;
+pub mod error;
 
+pub mod set1;
+pub mod set2;
+pub mod set3;
+pub mod set4;
+pub mod set5;
+pub mod set6;
+pub mod set7;
+pub mod set8;
@@ -1,21 +1,126 @@
+use base64_light::base64_encode_bytes;
 
-use base64_light::{base64_encode_bytes, base64_decode};
+use crate::error::Error;
 
 
+// Strict decoding only accepts characters from the alphabet, and for base64 the unused bits of the last character must be zero.
+// Lenient decoding additionally skips ASCII whitespace, which is needed for the multi-line blobs in the challenges,
+// and ignores the unused bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeMode {
+    Strict,
+    Lenient,
+}
+
+const BASE64_PADDING: char = '=';
+
 pub fn bytes_to_hex(bytes: &[u8]) -> String {
     hex::encode(bytes)
 }
 
-pub fn hex_to_bytes(hex: &str) -> Vec<u8> {
-    hex::decode(hex).unwrap()
+pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, Error> {
+    hex_to_bytes_with_mode(hex, DecodeMode::Strict)
+}
+
+pub fn hex_to_bytes_with_mode(hex: &str, mode: DecodeMode) -> Result<Vec<u8>, Error> {
+    let mut digits = Vec::with_capacity(hex.len());
+    for (position, character) in hex.char_indices() {
+        if mode == DecodeMode::Lenient && character.is_ascii_whitespace() {
+            continue;
+        }
+
+        match character.to_digit(16) {
+            Some(digit) => digits.push(digit as u8),
+            None => return Err(Error::InvalidCharacter { character, position }),
+        }
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err(Error::OddLength { length: digits.len() });
+    }
+
+    Ok(digits.chunks(2).map(|pair| (pair[0] << 4) | pair[1]).collect())
 }
 
 pub fn bytes_to_base64(bytes: &[u8]) -> String {
     base64_encode_bytes(bytes)
 }
 
-pub fn base64_to_bytes(base64: &str) -> Vec<u8> {
-    base64_decode(base64)
+pub fn base64_to_bytes(base64: &str) -> Result<Vec<u8>, Error> {
+    base64_to_bytes_with_mode(base64, DecodeMode::Strict)
+}
+
+pub fn base64_to_bytes_with_mode(base64: &str, mode: DecodeMode) -> Result<Vec<u8>, Error> {
+    let mut values = Vec::with_capacity(base64.len());
+    let mut num_padding = 0;
+    let mut last_value_position = 0;
+
+    for (position, character) in base64.char_indices() {
+        if mode == DecodeMode::Lenient && character.is_ascii_whitespace() {
+            continue;
+        }
+
+        if character == BASE64_PADDING {
+            // padding may only complete a final quantum holding 2 or 3 characters
+            let allowed_padding = match values.len() % 4 {
+                2 => 2,
+                3 => 1,
+                _ => 0,
+            };
+            num_padding += 1;
+            if num_padding > allowed_padding {
+                return Err(Error::InvalidPadding { position });
+            }
+            continue;
+        }
+
+        // nothing but padding may follow the first padding character
+        if num_padding > 0 {
+            return Err(Error::InvalidPadding { position });
+        }
+
+        match base64_value(character) {
+            Some(value) => values.push(value),
+            None => return Err(Error::InvalidCharacter { character, position }),
+        }
+        last_value_position = position;
+    }
+
+    // the input must be made of whole 4 character quanta
+    if (values.len() + num_padding) % 4 != 0 {
+        return Err(Error::InvalidPadding { position: base64.len() });
+    }
+
+    // each character holds 6 bits, emit a byte every time 8 bits are available
+    let mut bytes = Vec::with_capacity(values.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut num_bits = 0;
+    for value in values {
+        buffer = (buffer << 6) | value as u32;
+        num_bits += 6;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            bytes.push((buffer >> num_bits) as u8);
+        }
+    }
+
+    // a canonical encoding leaves the bits that do not make up a whole byte zeroed
+    if mode == DecodeMode::Strict && buffer & ((1 << num_bits) - 1) != 0 {
+        return Err(Error::InvalidPadding { position: last_value_position });
+    }
+
+    Ok(bytes)
+}
+
+fn base64_value(character: char) -> Option<u8> {
+    match character {
+        'A'..='Z' => Some(character as u8 - b'A'),
+        'a'..='z' => Some(character as u8 - b'a' + 26),
+        '0'..='9' => Some(character as u8 - b'0' + 52),
+        '+' => Some(62),
+        '/' => Some(63),
+        _ => None,
+    }
 }
 
 
@@ -29,17 +134,51 @@ mod tests {
         let input_hex = "49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d";
         let input_base64 = "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t";
 
-        let result_str = String::from_utf8(hex_to_bytes(input_hex)).unwrap();
+        let result_str = String::from_utf8(hex_to_bytes(input_hex).unwrap()).unwrap();
         println!("{}", result_str);
         assert_eq!("I'm killing your brain like a poisonous mushroom", result_str);
 
         // convert hex to base64
-        let result_base64 = bytes_to_base64(&hex_to_bytes(input_hex));
+        let result_base64 = bytes_to_base64(&hex_to_bytes(input_hex).unwrap());
         assert_eq!(input_base64, result_base64);
 
         // convert base64 to hex
-        let result_hex = bytes_to_hex(&base64_to_bytes(input_base64));
+        let result_hex = bytes_to_hex(&base64_to_bytes(input_base64).unwrap());
         assert_eq!(input_hex, result_hex)
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_hex_decode_errors() {
+        assert_eq!(Err(Error::OddLength { length: 3 }), hex_to_bytes("abc"));
+        assert_eq!(Err(Error::InvalidCharacter { character: 'g', position: 2 }), hex_to_bytes("abgd"));
+        assert_eq!(Err(Error::InvalidCharacter { character: '\n', position: 2 }), hex_to_bytes("ab\ncd"));
+
+        assert_eq!(Ok(vec![0xab, 0xcd]), hex_to_bytes_with_mode("ab\ncd ", DecodeMode::Lenient));
+    }
+
+    #[test]
+    fn test_base64_decode_errors() {
+        assert_eq!(Ok(b"f".to_vec()), base64_to_bytes("Zg=="));
+        assert_eq!(Ok(b"fo".to_vec()), base64_to_bytes("Zm8="));
+        assert_eq!(Ok(b"foo".to_vec()), base64_to_bytes("Zm9v"));
+        assert_eq!(Ok(Vec::new()), base64_to_bytes(""));
+
+        assert_eq!(Err(Error::InvalidCharacter { character: '*', position: 3 }), base64_to_bytes("Zm9*"));
+        assert_eq!(Err(Error::InvalidPadding { position: 3 }), base64_to_bytes("Zm=v"));
+        assert_eq!(Err(Error::InvalidPadding { position: 1 }), base64_to_bytes("Z==="));
+        assert_eq!(Err(Error::InvalidPadding { position: 3 }), base64_to_bytes("Zm9"));
+        assert_eq!(Err(Error::InvalidPadding { position: 4 }), base64_to_bytes("Zm9v="));
+        assert_eq!(Err(Error::InvalidPadding { position: 1 }), base64_to_bytes("Zh=="));
+        assert_eq!(Err(Error::InvalidPadding { position: 2 }), base64_to_bytes("Zm9="));
+        assert_eq!(Ok(b"f".to_vec()), base64_to_bytes_with_mode("Zh==", DecodeMode::Lenient));
+    }
+
+    #[test]
+    fn test_base64_decode_lenient() {
+        let input_base64 = "Zm9v\nYmFy\r\n YQ==\n";
+
+        assert_eq!(Err(Error::InvalidCharacter { character: '\n', position: 4 }), base64_to_bytes(input_base64));
+        assert_eq!(Ok(b"foobara".to_vec()), base64_to_bytes_with_mode(input_base64, DecodeMode::Lenient));
+    }
+
+}
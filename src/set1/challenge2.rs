@@ -1,7 +1,4 @@
 
-use crate::set1::challenge1::hex_to_bytes;
-
-
 pub fn fixed_xor(x: &[u8], y: &[u8]) -> Vec<u8> {
     if x.len() != y.len() {
         panic!("x and y are not equal length");
@@ -20,12 +17,13 @@ pub fn fixed_xor(x: &[u8], y: &[u8]) -> Vec<u8> {
 mod tests {
 
     use super::*;
+    use crate::set1::challenge1::hex_to_bytes;
 
     #[test]
     fn run_challenge2() {
-        let input1 = hex_to_bytes("1c0111001f010100061a024b53535009181c");
-        let input2 = hex_to_bytes("686974207468652062756c6c277320657965");
-        let expected = hex_to_bytes("746865206b696420646f6e277420706c6179");
+        let input1 = hex_to_bytes("1c0111001f010100061a024b53535009181c").unwrap();
+        let input2 = hex_to_bytes("686974207468652062756c6c277320657965").unwrap();
+        let expected = hex_to_bytes("746865206b696420646f6e277420706c6179").unwrap();
 
         let result = fixed_xor(&input1, &input2);
         assert_eq!(expected, result);
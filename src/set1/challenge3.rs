@@ -1,7 +1,6 @@
 use std::collections::HashMap;
 use character_frequency::{CaseSense, character_frequencies_w_case};
 
-use crate::set1::challenge1::hex_to_bytes;
 use crate::set1::challenge2::fixed_xor;
 
 // Score text for probability of being english.
@@ -50,7 +49,7 @@ pub fn single_char_xor_find_best_candidate(ciphertext: &[u8]) -> (f64, u8, Strin
         // create a key stream of equal length
         let key = vec![i; ciphertext.len()];
 
-        let result = fixed_xor(&ciphertext, &key);
+        let result = fixed_xor(ciphertext, &key);
         let result_str = String::from_utf8(result);
 
         if let Ok(value) = result_str { // ignore the values that don't parse as text
@@ -70,10 +69,11 @@ pub fn single_char_xor_find_best_candidate(ciphertext: &[u8]) -> (f64, u8, Strin
 mod tests {
 
     use super::*;
+    use crate::set1::challenge1::hex_to_bytes;
 
     #[test]
     fn run_challenge3() {
-        let ciphertext = hex_to_bytes("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736");
+        let ciphertext = hex_to_bytes("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736").unwrap();
         let best_candidate = single_char_xor_find_best_candidate(&ciphertext);
         assert_eq!("Cooking MC's like a pound of bacon", best_candidate.2);
 
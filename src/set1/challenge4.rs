@@ -1,12 +1,9 @@
 
-use crate::set1::challenge1::hex_to_bytes;
-use crate::set1::challenge3::single_char_xor_find_best_candidate;
-
-
 #[cfg(test)]
 mod tests {
 
-    use super::*;
+    use crate::set1::challenge1::hex_to_bytes;
+    use crate::set1::challenge3::single_char_xor_find_best_candidate;
 
 
     #[test]
@@ -340,7 +337,7 @@ e03555453d1e31775f37331823164c341c09e310463438481019fb0b12fa
 32042f46431d2c44607934ed180c1028136a5f2b26092e3b2c4e2930585a".lines();
 
         let mut best_candidate = (1f64, 0, "".to_owned());
-        for c in ciphertexts.map(|e| hex_to_bytes(e)) {
+        for c in ciphertexts.map(|e| hex_to_bytes(e).unwrap()) {
             let result = single_char_xor_find_best_candidate(&c);
             if result.0 < best_candidate.0 {
                 best_candidate = result;
@@ -1,8 +1,4 @@
 
-use crate::set1::challenge1::hex_to_bytes;
-use crate::set1::challenge2::fixed_xor;
-
-
 pub fn repeat_key(in_key: &[u8], out_key: &mut [u8]) {
     if in_key.is_empty() {
         panic!("in_key cannot be empty");
@@ -21,13 +17,15 @@ pub fn repeat_key(in_key: &[u8], out_key: &mut [u8]) {
 mod tests {
 
     use super::*;
+    use crate::set1::challenge1::hex_to_bytes;
+    use crate::set1::challenge2::fixed_xor;
 
 
     #[test]
     fn run_challenge5() {
         let input_plaintext = "Burning 'em, if you ain't quick and nimble
 I go crazy when I hear a cymbal".as_bytes();
-        let expected_ciphertext = hex_to_bytes("0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f");
+        let expected_ciphertext = hex_to_bytes("0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f").unwrap();
 
         let key = "ICE".as_bytes();
         let mut repeating_key = vec![0u8; input_plaintext.len()];
@@ -1,5 +1,4 @@
 
-use crate::set1::challenge1::base64_to_bytes;
 use crate::set1::challenge2::fixed_xor;
 use crate::set1::challenge3::single_char_xor_find_best_candidate;
 use crate::set1::challenge5::repeat_key;
@@ -16,9 +15,9 @@ pub fn edit_distance(first: &[u8], second: &[u8]) -> u32 {
         .sum()
 }
 
-pub fn crack_repeating_key_xor(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
+pub fn find_repeating_key(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
     // Now that you probably know the KEYSIZE: break the ciphertext into blocks of KEYSIZE length.
-    let ciphertext_chunks: Vec<&[u8]> = ciphertext.chunks(key_size as usize).collect();
+    let ciphertext_chunks: Vec<&[u8]> = ciphertext.chunks(key_size).collect();
 
     // Now transpose the blocks: make a block that is the first byte of every block, and a block that is the second byte of every block, and so on.
     let mut transposed_chunks: Vec<Vec<u8>> = Vec::new();
@@ -26,7 +25,7 @@ pub fn crack_repeating_key_xor(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
         let tmp: Vec<u8> = Vec::new();
         transposed_chunks.push(tmp);
     }
-    for (_i, x) in ciphertext_chunks.iter().enumerate() {
+    for x in ciphertext_chunks.iter() {
         for (j, y) in x.iter().enumerate() {
             let tmp = transposed_chunks.get_mut(j).unwrap();
             tmp.push(*y);
@@ -40,10 +39,16 @@ pub fn crack_repeating_key_xor(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
         key.push(result.1);
     }
 
+    key
+}
+
+pub fn crack_repeating_key_xor(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
+    let key = find_repeating_key(key_size, ciphertext);
+
     // For each block, the single-byte XOR key that produces the best looking histogram is the repeating-key XOR key byte for that block. Put them together and you have the key.
     let mut full_key = vec![0u8; ciphertext.len()];
     repeat_key(&key, &mut full_key);
-    fixed_xor(&full_key, &ciphertext)
+    fixed_xor(&full_key, ciphertext)
 }
 
 
@@ -51,6 +56,7 @@ pub fn crack_repeating_key_xor(key_size: usize, ciphertext: &[u8]) -> Vec<u8> {
 mod tests {
 
     use super::*;
+    use crate::set1::challenge1::{base64_to_bytes_with_mode, DecodeMode};
 
 
     #[test]
@@ -67,7 +73,7 @@ mod tests {
     #[test]
     fn run_challenge6() {
 
-        let ciphertext = base64_to_bytes("HUIfTQsPAh9PE048GmllH0kcDk4TAQsHThsBFkU2AB4BSWQgVB0dQzNTTmVS
+        let ciphertext = base64_to_bytes_with_mode("HUIfTQsPAh9PE048GmllH0kcDk4TAQsHThsBFkU2AB4BSWQgVB0dQzNTTmVS
 BgBHVBwNRU0HBAxTEjwMHghJGgkRTxRMIRpHKwAFHUdZEQQJAGQmB1MANxYG
 DBoXQR0BUlQwXwAgEwoFR08SSAhFTmU+Fgk4RQYFCBpGB08fWXh+amI2DB0P
 QQ1IBlUaGwAdQnQEHgFJGgkRAlJ6f0kASDoAGhNJGk9FSA8dDVMEOgFSGQEL
@@ -130,7 +136,7 @@ DxsJSw1ICFUdBgpTJjsIF00GAE1ULB1NPRpPLF5JAgJUVAUAAAYKCAFFXjUe
 DBBOFRwOBgA+T04pC0kDElMdC0VXBgYdFkU2CgtNEAEUVBwTWXhTVG5SGg8e
 AB0cRSo+AwgKRSANExlJCBQaBAsANU9TKxFJL0dMHRwRTAtPBRwQMAAATQcB
 FlRlIkw5QwA2GggaR0YBBg5ZTgIcAAw3SVIaAQcVEU8QTyEaYy0fDE4ITlhI
-Jk8DCkkcC3hFMQIEC0EbAVIqCFZBO1IdBgZUVA4QTgUWSR4QJwwRTWM=");
+Jk8DCkkcC3hFMQIEC0EbAVIqCFZBO1IdBgZUVA4QTgUWSR4QJwwRTWM=", DecodeMode::Lenient).unwrap();
 
         let mut results = Vec::new();
 
@@ -1,27 +1,240 @@
+use crate::error::Error;
+use crate::set1::challenge1::{base64_to_bytes_with_mode, DecodeMode};
+use crate::set2::challenge9::unpad_pkcs7;
 
-use openssl::error::ErrorStack;
-use openssl::symm::Cipher;
-use openssl::symm::decrypt;
 
-use crate::set1::challenge1::base64_to_bytes;
+pub const AES_BLOCK_SIZE: usize = 16;
 
+// Multiply two elements of GF(2^8) modulo the AES polynomial x^8 + x^4 + x^3 + x + 1.
+pub const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let high_bit = a & 0x80;
+        a <<= 1;
+        if high_bit != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
 
-pub fn aes_128_ecb_decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
-    decrypt(Cipher::aes_128_ecb(), key, None, &ciphertext)
+// The S-box maps each byte to its multiplicative inverse in GF(2^8) followed by an affine transformation.
+const fn build_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        // a^254 is the inverse of a, and maps 0 to 0
+        let mut inverse = 1u8;
+        let mut j = 0;
+        while j < 254 {
+            inverse = gf_mul(inverse, i as u8);
+            j += 1;
+        }
+        sbox[i] = inverse
+            ^ inverse.rotate_left(1)
+            ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3)
+            ^ inverse.rotate_left(4)
+            ^ 0x63;
+        i += 1;
+    }
+    sbox
 }
 
+const fn build_inverse_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inverse_sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inverse_sbox[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inverse_sbox
+}
 
-#[cfg(test)]
-mod tests {
+pub const SBOX: [u8; 256] = build_sbox();
+pub const INVERSE_SBOX: [u8; 256] = build_inverse_sbox(&SBOX);
 
-    use super::*;
+// Round constants used by the key schedule, successive powers of x in GF(2^8).
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
 
+// The state is stored column by column, so byte (row, column) is at index row + 4 * column.
+pub fn sub_bytes(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for b in state.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
 
-    #[test]
-    fn run_challenge7() {
-        let key = "YELLOW SUBMARINE".as_bytes();
+pub fn inverse_sub_bytes(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for b in state.iter_mut() {
+        *b = INVERSE_SBOX[*b as usize];
+    }
+}
+
+// Row r is rotated left by r positions.
+pub fn shift_rows(state: &mut [u8; AES_BLOCK_SIZE]) {
+    let original = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * column] = original[row + 4 * ((column + row) % 4)];
+        }
+    }
+}
+
+pub fn inverse_shift_rows(state: &mut [u8; AES_BLOCK_SIZE]) {
+    let original = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * ((column + row) % 4)] = original[row + 4 * column];
+        }
+    }
+}
+
+// Multiply by x in GF(2^8).
+fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+// Each column is multiplied by the fixed polynomial {03}x^3 + {01}x^2 + {01}x + {02}.
+pub fn mix_columns(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let (a0, a1, a2, a3) = (column[0], column[1], column[2], column[3]);
+        let t = a0 ^ a1 ^ a2 ^ a3;
+        column[0] = a0 ^ t ^ xtime(a0 ^ a1);
+        column[1] = a1 ^ t ^ xtime(a1 ^ a2);
+        column[2] = a2 ^ t ^ xtime(a2 ^ a3);
+        column[3] = a3 ^ t ^ xtime(a3 ^ a0);
+    }
+}
 
-        let ciphertext = base64_to_bytes("CRIwqt4+szDbqkNY+I0qbDe3LQz0wiw0SuxBQtAM5TDdMbjCMD/venUDW9BL
+// Multiplying by {04}x^2 + {05} first turns the inverse polynomial {0b}x^3 + {0d}x^2 + {09}x + {0e} into the forward one.
+pub fn inverse_mix_columns(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let u = xtime(xtime(column[0] ^ column[2]));
+        let v = xtime(xtime(column[1] ^ column[3]));
+        column[0] ^= u;
+        column[1] ^= v;
+        column[2] ^= u;
+        column[3] ^= v;
+    }
+    mix_columns(state);
+}
+
+pub fn add_round_key(state: &mut [u8; AES_BLOCK_SIZE], round_key: &[u8; AES_BLOCK_SIZE]) {
+    for (b, k) in state.iter_mut().zip(round_key.iter()) {
+        *b ^= k;
+    }
+}
+
+// Expand a 16, 24 or 32 byte key into the round keys, one per round plus the initial whitening key.
+pub fn expand_key(key: &[u8]) -> Result<Vec<[u8; AES_BLOCK_SIZE]>, Error> {
+    if !matches!(key.len(), 16 | 24 | 32) {
+        return Err(Error::InvalidKeyLength { length: key.len() });
+    }
+
+    let key_words = key.len() / 4;
+    let rounds = key_words + 6;
+    let total_words = 4 * (rounds + 1);
+
+    let mut words: Vec<[u8; 4]> = key.chunks(4).map(|w| [w[0], w[1], w[2], w[3]]).collect();
+    for i in key_words..total_words {
+        let mut word = words[i - 1];
+        if i % key_words == 0 {
+            // RotWord, SubWord and the round constant
+            word.rotate_left(1);
+            word = word.map(|b| SBOX[b as usize]);
+            word[0] ^= RCON[i / key_words - 1];
+        } else if key_words > 6 && i % key_words == 4 {
+            word = word.map(|b| SBOX[b as usize]);
+        }
+
+        let previous = words[i - key_words];
+        words.push([word[0] ^ previous[0], word[1] ^ previous[1], word[2] ^ previous[2], word[3] ^ previous[3]]);
+    }
+
+    Ok(words.chunks(4)
+        .map(|w| {
+            let mut round_key = [0u8; AES_BLOCK_SIZE];
+            for (i, word) in w.iter().enumerate() {
+                round_key[4 * i..4 * i + 4].copy_from_slice(word);
+            }
+            round_key
+        })
+        .collect())
+}
+
+// AES-128, AES-192 or AES-256 depending on the key length.
+#[derive(Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; AES_BLOCK_SIZE]>,
+}
+
+impl Aes {
+    pub fn new(key: &[u8]) -> Result<Aes, Error> {
+        Ok(Aes { round_keys: expand_key(key)? })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn round_keys(&self) -> &[[u8; AES_BLOCK_SIZE]] {
+        &self.round_keys
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; AES_BLOCK_SIZE]) {
+        let rounds = self.rounds();
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..rounds {
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+        // the final round skips MixColumns
+        sub_bytes(block);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[rounds]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; AES_BLOCK_SIZE]) {
+        let rounds = self.rounds();
+        add_round_key(block, &self.round_keys[rounds]);
+        inverse_shift_rows(block);
+        inverse_sub_bytes(block);
+        for round in (1..rounds).rev() {
+            add_round_key(block, &self.round_keys[round]);
+            inverse_mix_columns(block);
+            inverse_shift_rows(block);
+            inverse_sub_bytes(block);
+        }
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+pub fn aes_128_ecb_decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.len() != 16 {
+        return Err(Error::InvalidKeyLength { length: key.len() });
+    }
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(AES_BLOCK_SIZE) {
+        return Err(Error::InvalidBlockLength { length: ciphertext.len(), block_size: AES_BLOCK_SIZE });
+    }
+
+    let aes = Aes::new(key)?;
+    let mut plaintext = ciphertext.to_vec();
+    for chunk in plaintext.chunks_mut(AES_BLOCK_SIZE) {
+        aes.decrypt_block(chunk.try_into().unwrap());
+    }
+
+    Ok(unpad_pkcs7(AES_BLOCK_SIZE as u8, &plaintext)?)
+}
+
+const CHALLENGE7_KEY: &str = "YELLOW SUBMARINE";
+
+const CHALLENGE7_CIPHERTEXT_BASE64: &str = "CRIwqt4+szDbqkNY+I0qbDe3LQz0wiw0SuxBQtAM5TDdMbjCMD/venUDW9BL
 PEXODbk6a48oMbAY6DDZsuLbc0uR9cp9hQ0QQGATyyCESq2NSsvhx5zKlLtz
 dsnfK5ED5srKjK7Fz4Q38/ttd+stL/9WnDzlJvAo7WBsjI5YJc2gmAYayNfm
 CW2lhZE/ZLG0CBD2aPw0W417QYb4cAIOW92jYRiJ4PTsBBHDe8o4JwqaUac6
@@ -84,7 +297,49 @@ zGMaKbTG4dns1OFB1g7NCiPb6s1lv0/lHFAF6HwoYV/FPSL/pirxyDSBb/FR
 RA3PIfmvGfMUGFVWlyS7+O73l5oIJHxuaJrR4EenzAu4Avpa5d+VuiYbM10a
 LaVegVPvFn4pCP4U/Nbbw4OTCFX2HKmWEiVBB0O3J9xwXWpxN1Vr5CDi75Fq
 NhxYCjgSJzWOUD34Y1dAfcj57VINmQVEWyc8Tch8vg9MnHGCOfOjRqp0VGyA
-S15AVD2QS1V6fhRimJSVyT6QuGb8tKRsl2N+a2Xze36vgMhw7XK7zh//jC2H");
+S15AVD2QS1V6fhRimJSVyT6QuGb8tKRsl2N+a2Xze36vgMhw7XK7zh//jC2H";
+
+pub fn challenge7_ciphertext() -> Vec<u8> {
+    base64_to_bytes_with_mode(CHALLENGE7_CIPHERTEXT_BASE64, DecodeMode::Lenient).unwrap()
+}
+
+// The decrypted challenge7 text, handy as a sizeable English plaintext for later challenges.
+pub fn challenge7_plaintext() -> Vec<u8> {
+    aes_128_ecb_decrypt(CHALLENGE7_KEY.as_bytes(), &challenge7_ciphertext()).unwrap()
+}
+
+// OpenSSL implementation of the same primitive, only used to cross-check the native AES.
+#[cfg(feature = "openssl")]
+pub fn openssl_aes_ecb_encrypt_no_padding(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+    use openssl::symm::{Cipher, Crypter, Mode};
+
+    let cipher = match key.len() {
+        16 => Cipher::aes_128_ecb(),
+        24 => Cipher::aes_192_ecb(),
+        _ => Cipher::aes_256_ecb(),
+    };
+    let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, None)?;
+    crypter.pad(false);
+    let mut out = vec![0; plaintext.len() + cipher.block_size()];
+    let count = crypter.update(plaintext, &mut out)?;
+    let rest = crypter.finalize(&mut out[count..])?;
+    out.truncate(count + rest);
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::set1::challenge1::{bytes_to_hex, hex_to_bytes};
+    use super::*;
+
+
+    #[test]
+    fn run_challenge7() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+
+        let ciphertext = challenge7_ciphertext();
         //println!("{:?}", ciphertext);
 
         let plaintext_bytes = aes_128_ecb_decrypt(key, &ciphertext).unwrap();
@@ -94,4 +349,69 @@ S15AVD2QS1V6fhRimJSVyT6QuGb8tKRsl2N+a2Xze36vgMhw7XK7zh//jC2H");
         println!("{}", plaintext);
     }
 
+
+    #[test]
+    fn test_sbox() {
+        assert_eq!(0x63, SBOX[0x00]);
+        assert_eq!(0x7c, SBOX[0x01]);
+        assert_eq!(0xed, SBOX[0x53]);
+        assert_eq!(0x16, SBOX[0xff]);
+        assert_eq!(0x53, INVERSE_SBOX[0xed]);
+    }
+
+    #[test]
+    fn test_key_expansion() {
+        // FIPS-197 Appendix A.1
+        let key = hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let round_keys = expand_key(&key).unwrap();
+        assert_eq!(round_keys, Aes::new(&key).unwrap().round_keys());
+
+        assert_eq!(11, round_keys.len());
+        assert_eq!(hex_to_bytes("a0fafe1788542cb123a339392a6c7605").unwrap(), round_keys[1]);
+        assert_eq!(hex_to_bytes("d014f9a8c9ee2589e13f0cc8b6630ca6").unwrap(), round_keys[10]);
+
+        assert_eq!(Err(Error::InvalidKeyLength { length: 15 }), expand_key(&[0u8; 15]));
+    }
+
+    #[test]
+    fn test_aes_fips_197_vectors() {
+        // FIPS-197 Appendix C
+        let plaintext = hex_to_bytes("00112233445566778899aabbccddeeff").unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a", 10),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191", 12),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089", 14),
+        ];
+
+        for (key, expected_ciphertext, rounds) in vectors {
+            let aes = Aes::new(&hex_to_bytes(key).unwrap()).unwrap();
+            assert_eq!(rounds, aes.rounds());
+
+            let mut block: [u8; AES_BLOCK_SIZE] = plaintext.clone().try_into().unwrap();
+            aes.encrypt_block(&mut block);
+            assert_eq!(expected_ciphertext, bytes_to_hex(&block));
+
+            aes.decrypt_block(&mut block);
+            assert_eq!(plaintext, block);
+        }
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn test_aes_matches_openssl() {
+        let plaintext: Vec<u8> = (0u8..64).collect();
+
+        for key_size in [16, 24, 32] {
+            let key: Vec<u8> = (0..key_size).map(|i| (i * 7) as u8).collect();
+            let aes = Aes::new(&key).unwrap();
+
+            let mut ciphertext = plaintext.clone();
+            for chunk in ciphertext.chunks_mut(AES_BLOCK_SIZE) {
+                aes.encrypt_block(chunk.try_into().unwrap());
+            }
+
+            assert_eq!(openssl_aes_ecb_encrypt_no_padding(&key, &plaintext).unwrap(), ciphertext);
+        }
+    }
+
 }
\ No newline at end of file
@@ -1,13 +1,29 @@
 
 use std::collections::HashSet;
 
-use crate::set1::challenge1::{bytes_to_hex, hex_to_bytes};
+
+
+// Count the blocks that are identical to an earlier block of the ciphertext.
+// ECB encrypts equal plaintext blocks to equal ciphertext blocks so any repetition is a strong hint of ECB mode.
+pub fn count_repeated_blocks(ciphertext: &[u8], block_size: usize) -> usize {
+    let mut unique_chunks = HashSet::new();
+    ciphertext.chunks(block_size)
+        .filter(|e| !unique_chunks.insert(*e))
+        .count()
+}
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::set1::challenge1::{bytes_to_hex, hex_to_bytes};
+
 
+    #[test]
+    fn test_count_repeated_blocks() {
+        assert_eq!(0, count_repeated_blocks("ABCDEFGHIJKL".as_bytes(), 4));
+        assert_eq!(2, count_repeated_blocks("ABCDABCDEFGHABCD".as_bytes(), 4));
+    }
 
     #[test]
     fn run_challenge8() {
@@ -221,20 +237,13 @@ a6cadd53a2621482b7d66ecc82dc4ea6431bc0191c3801ac6b705df38c7fffe469043e5002096aca
         let block_size: usize = 16;
 
         let mut result = (9999999999999, String::new());
-        for (i, ciphertext) in ciphertexts.map(|c| hex_to_bytes(c)).enumerate() {
-
-            let ciphertext_chunks: Vec<&[u8]> = ciphertext.chunks(block_size).collect();
-            //let num_chunks = ciphertext_chunks.len();
+        for (i, ciphertext) in ciphertexts.map(|c| hex_to_bytes(c).unwrap()).enumerate() {
 
-            let mut unique_chunks = HashSet::new();
-            for e in ciphertext_chunks {
-                if !unique_chunks.insert(e) {
-                    let c_hex = bytes_to_hex(&ciphertext);
-                    println!("Detected AES ECB at line = {}, ciphertext = {}", i, c_hex);
-                    result.0 = i;
-                    result.1 = c_hex;
-                    break;
-                }
+            if count_repeated_blocks(&ciphertext, block_size) > 0 {
+                let c_hex = bytes_to_hex(&ciphertext);
+                println!("Detected AES ECB at line = {}, ciphertext = {}", i, c_hex);
+                result.0 = i;
+                result.1 = c_hex;
             }
         }
 
@@ -1,8 +1,8 @@
-pub(crate) mod challenge1;
-pub(crate) mod challenge2;
-pub(crate) mod challenge3;
-pub(crate) mod challenge4;
-pub(crate) mod challenge5;
-pub(crate) mod challenge6;
-pub(crate) mod challenge7;
-pub(crate) mod challenge8;
\ No newline at end of file
+pub mod challenge1;
+pub mod challenge2;
+pub mod challenge3;
+pub mod challenge4;
+pub mod challenge5;
+pub mod challenge6;
+pub mod challenge7;
+pub mod challenge8;
\ No newline at end of file
@@ -1,84 +1,346 @@
-
-use openssl::error::ErrorStack;
-use openssl::symm::{Cipher, Crypter, Mode};
-use openssl::symm::Mode::{Decrypt, Encrypt};
-
+use crate::error::Error;
 use crate::set1::challenge2::fixed_xor;
-use crate::set2::challenge9::pad_using_pkcs7;
+use crate::set1::challenge7::{Aes, SBOX};
+use crate::set2::challenge9::{Padding, Pkcs7};
 
 const BLOCK_SIZE_BYTES: usize = 16;
 
-fn cipher(
-    t: Cipher,
-    mode: Mode,
-    key: &[u8],
-    iv: Option<&[u8]>,
-    data: &[u8],
-) -> Result<Vec<u8>, ErrorStack> {
-    let mut c = Crypter::new(t, mode, key, iv)?;
-    c.pad(false); // disable padding as we are only using this to encrypt/decrypt single block at a time
-    let mut out = vec![0; data.len() + t.block_size()];
-    let count = c.update(data, &mut out)?;
-    let rest = c.finalize(&mut out[count..])?;
-    out.truncate(count + rest);
-    Ok(out)
+// A keyed permutation on fixed size blocks, the modes of operation below work with any implementation.
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: &mut [u8]);
+    fn decrypt_block(&self, block: &mut [u8]);
 }
 
-pub fn aes_128_ecb_encrypt_no_padding(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
-    cipher(Cipher::aes_128_ecb(), Encrypt, key, None, plaintext)
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE_BYTES
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        Aes::encrypt_block(self, block.try_into().expect("block must be 16 bytes"));
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        Aes::decrypt_block(self, block.try_into().expect("block must be 16 bytes"));
+    }
 }
 
-pub fn aes_128_ecb_decrypt_no_padding(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
-    cipher(Cipher::aes_128_ecb(), Decrypt, key, None, ciphertext)
+// Toy Feistel network on small blocks (e.g. 4 or 8 bytes) for teaching, NOT secure.
+// Each round mixes one half into the other using the AES S-box and a round key taken from the key bytes.
+pub struct ToyFeistel {
+    block_size: usize,
+    round_keys: Vec<Vec<u8>>,
 }
 
-pub fn aes_128_cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, ErrorStack>
-{
-    if key.len() != BLOCK_SIZE_BYTES || iv.len() != BLOCK_SIZE_BYTES {
-        panic!("key and iv must be equal to the block size");
+impl ToyFeistel {
+    const ROUNDS: usize = 8;
+
+    pub fn new(block_size: usize, key: &[u8]) -> Result<ToyFeistel, Error> {
+        // the block is split into two equal, non-empty halves
+        if block_size == 0 || !block_size.is_multiple_of(2) {
+            return Err(Error::UnsupportedBlockSize { block_size });
+        }
+        if key.is_empty() {
+            return Err(Error::InvalidKeyLength { length: 0 });
+        }
+
+        let half = block_size / 2;
+        let round_keys = (0..Self::ROUNDS)
+            .map(|round| (0..half).map(|i| key[(round * half + i) % key.len()] ^ round as u8).collect())
+            .collect();
+
+        Ok(ToyFeistel { block_size, round_keys })
+    }
+
+    fn round_function(half: &[u8], round_key: &[u8]) -> Vec<u8> {
+        (0..half.len())
+            .map(|i| SBOX[(half[i] ^ round_key[i] ^ half[(i + 1) % half.len()].rotate_left(3)) as usize])
+            .collect()
+    }
+}
+
+impl BlockCipher for ToyFeistel {
+    fn block_size(&self) -> usize {
+        self.block_size
     }
 
-    let padded_plaintext = pad_using_pkcs7(BLOCK_SIZE_BYTES as u8, plaintext);
-    let plaintext_blocks: Vec<&[u8]> = padded_plaintext.chunks(BLOCK_SIZE_BYTES).collect();
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let (left, right) = block.split_at_mut(self.block_size / 2);
+        for round_key in self.round_keys.iter() {
+            // (L, R) -> (R, L ^ F(R))
+            let f = Self::round_function(right, round_key);
+            for (l, x) in left.iter_mut().zip(f) {
+                *l ^= x;
+            }
+            left.swap_with_slice(right);
+        }
+    }
 
-    let mut ciphertext = Vec::with_capacity(padded_plaintext.len());
-    let mut next_iv = iv.to_vec();
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let (left, right) = block.split_at_mut(self.block_size / 2);
+        for round_key in self.round_keys.iter().rev() {
+            // (R, L ^ F(R)) -> (L, R)
+            left.swap_with_slice(right);
+            let f = Self::round_function(right, round_key);
+            for (l, x) in left.iter_mut().zip(f) {
+                *l ^= x;
+            }
+        }
+    }
+}
 
-    for block in plaintext_blocks {
-        let mut ciphertext_block = aes_128_ecb_encrypt_no_padding(key, &fixed_xor(&next_iv, block))?;
-        next_iv = ciphertext_block.clone();
-        ciphertext.append(&mut ciphertext_block);
+fn check_whole_blocks(cipher: &impl BlockCipher, data: &[u8]) -> Result<(), Error> {
+    if !data.len().is_multiple_of(cipher.block_size()) {
+        return Err(Error::InvalidBlockLength { length: data.len(), block_size: cipher.block_size() });
     }
+    Ok(())
+}
 
+fn check_iv(cipher: &impl BlockCipher, iv: &[u8]) -> Result<(), Error> {
+    if iv.len() != cipher.block_size() {
+        return Err(Error::InvalidIvLength { length: iv.len(), block_size: cipher.block_size() });
+    }
+    Ok(())
+}
+
+fn xor_in_place(target: &mut [u8], other: &[u8]) {
+    for (t, o) in target.iter_mut().zip(other) {
+        *t ^= o;
+    }
+}
+
+// Treat the whole block as a big-endian integer and add one, wrapping around on overflow.
+pub fn increment_counter_block(counter: &mut [u8]) {
+    add_to_counter_block(counter, 1);
+}
+
+// Treat the whole block as a big-endian integer and add n, wrapping around on overflow.
+pub fn add_to_counter_block(counter: &mut [u8], n: u64) {
+    let mut carry = n as u128;
+    for b in counter.iter_mut().rev() {
+        if carry == 0 {
+            break;
+        }
+        let sum = *b as u128 + carry;
+        *b = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+// XOR data sitting at the given byte offset of a CTR stream with the keystream E(counter_block(0)), E(counter_block(1)), ...
+// Only the blocks covering the data are generated, so the stream can be entered anywhere.
+pub fn apply_ctr_keystream(cipher: &impl BlockCipher, counter_block: impl Fn(u64) -> Vec<u8>, offset: u64, data: &mut [u8]) {
+    let block_size = cipher.block_size();
+    let mut position = 0;
+    while position < data.len() {
+        let stream_offset = offset + position as u64;
+        let mut keystream_block = counter_block(stream_offset / block_size as u64);
+        cipher.encrypt_block(&mut keystream_block);
+        let start = (stream_offset % block_size as u64) as usize;
+        let len = (block_size - start).min(data.len() - position);
+
+        xor_in_place(&mut data[position..position + len], &keystream_block[start..]);
+        position += len;
+    }
+}
+
+pub fn ecb_encrypt(cipher: &impl BlockCipher, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_whole_blocks(cipher, plaintext)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    for block in ciphertext.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(block);
+    }
     Ok(ciphertext)
 }
 
-pub fn aes_128_cbc_decrypt(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, ErrorStack>
-{
-    if key.len() != BLOCK_SIZE_BYTES || iv.len() != BLOCK_SIZE_BYTES {
-        panic!("key and iv must be equal to the block size");
+pub fn ecb_decrypt(cipher: &impl BlockCipher, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_whole_blocks(cipher, ciphertext)?;
+
+    let mut plaintext = ciphertext.to_vec();
+    for block in plaintext.chunks_mut(cipher.block_size()) {
+        cipher.decrypt_block(block);
     }
+    Ok(plaintext)
+}
+
+// C[i] = E(P[i] ^ C[i-1]) with C[-1] = IV
+pub fn cbc_encrypt(cipher: &impl BlockCipher, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, plaintext)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    let mut previous = iv.to_vec();
+    for block in ciphertext.chunks_mut(cipher.block_size()) {
+        xor_in_place(block, &previous);
+        cipher.encrypt_block(block);
+        previous.copy_from_slice(block);
+    }
+    Ok(ciphertext)
+}
+
+// P[i] = D(C[i]) ^ C[i-1] with C[-1] = IV
+pub fn cbc_decrypt(cipher: &impl BlockCipher, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, ciphertext)?;
+
+    let mut plaintext = ciphertext.to_vec();
+    let mut previous = iv;
+    for (block, ciphertext_block) in plaintext.chunks_mut(cipher.block_size()).zip(ciphertext.chunks(cipher.block_size())) {
+        cipher.decrypt_block(block);
+        xor_in_place(block, previous);
+        previous = ciphertext_block;
+    }
+    Ok(plaintext)
+}
+
+// C[i] = E(P[i] ^ P[i-1] ^ C[i-1]) with P[-1] ^ C[-1] = IV
+pub fn pcbc_encrypt(cipher: &impl BlockCipher, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, plaintext)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    let mut chain = iv.to_vec();
+    for (block, plaintext_block) in ciphertext.chunks_mut(cipher.block_size()).zip(plaintext.chunks(cipher.block_size())) {
+        xor_in_place(block, &chain);
+        cipher.encrypt_block(block);
+        chain = fixed_xor(plaintext_block, block);
+    }
+    Ok(ciphertext)
+}
+
+// P[i] = D(C[i]) ^ P[i-1] ^ C[i-1] with P[-1] ^ C[-1] = IV
+pub fn pcbc_decrypt(cipher: &impl BlockCipher, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, ciphertext)?;
+
+    let mut plaintext = ciphertext.to_vec();
+    let mut chain = iv.to_vec();
+    for (block, ciphertext_block) in plaintext.chunks_mut(cipher.block_size()).zip(ciphertext.chunks(cipher.block_size())) {
+        cipher.decrypt_block(block);
+        xor_in_place(block, &chain);
+        chain = fixed_xor(block, ciphertext_block);
+    }
+    Ok(plaintext)
+}
 
-    let ciphertext_blocks: Vec<&[u8]> = ciphertext.chunks(BLOCK_SIZE_BYTES).collect();
-    let mut plaintext = Vec::with_capacity(ciphertext_blocks.len());
-    let mut next_iv = iv.to_vec();
+// C[i] = P[i] ^ E(C[i-1]) with C[-1] = IV, the final block may be partial.
+pub fn cfb_encrypt(cipher: &impl BlockCipher, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
 
-    for block in ciphertext_blocks {
-        let mut plaintext_block = fixed_xor(&next_iv, &aes_128_ecb_decrypt_no_padding(key, &block)?);
-        next_iv = block.to_vec();
-        plaintext.append(&mut plaintext_block);
+    let mut ciphertext = plaintext.to_vec();
+    let mut register = iv.to_vec();
+    for block in ciphertext.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(&mut register);
+        xor_in_place(block, &register);
+        register[..block.len()].copy_from_slice(block);
     }
+    Ok(ciphertext)
+}
 
-    // remove padding assuming PKCS#7 format
-    let num_padding_bytes = plaintext[plaintext.len() - 1]; // get the number of padding bytes from the last byte
-    plaintext.truncate(plaintext.len() - num_padding_bytes as usize);
+// P[i] = C[i] ^ E(C[i-1]) with C[-1] = IV, the final block may be partial.
+pub fn cfb_decrypt(cipher: &impl BlockCipher, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
 
+    let mut plaintext = ciphertext.to_vec();
+    let mut register = iv.to_vec();
+    for (block, ciphertext_block) in plaintext.chunks_mut(cipher.block_size()).zip(ciphertext.chunks(cipher.block_size())) {
+        cipher.encrypt_block(&mut register);
+        xor_in_place(block, &register);
+        register[..block.len()].copy_from_slice(ciphertext_block);
+    }
     Ok(plaintext)
 }
 
+// The keystream is E(IV), E(E(IV)), ... so encryption and decryption are the same operation.
+pub fn ofb_encrypt(cipher: &impl BlockCipher, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, iv)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    let mut register = iv.to_vec();
+    for block in ciphertext.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(&mut register);
+        xor_in_place(block, &register);
+    }
+    Ok(ciphertext)
+}
+
+pub fn ofb_decrypt(cipher: &impl BlockCipher, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    ofb_encrypt(cipher, iv, ciphertext)
+}
+
+// The keystream is E(counter), E(counter + 1), ... so encryption and decryption are the same operation.
+pub fn ctr_encrypt(cipher: &impl BlockCipher, initial_counter: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    check_iv(cipher, initial_counter)?;
+
+    let counter_block = |block_index| {
+        let mut counter = initial_counter.to_vec();
+        add_to_counter_block(&mut counter, block_index);
+        counter
+    };
+
+    let mut ciphertext = plaintext.to_vec();
+    apply_ctr_keystream(cipher, counter_block, 0, &mut ciphertext);
+    Ok(ciphertext)
+}
+
+pub fn ctr_decrypt(cipher: &impl BlockCipher, initial_counter: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    ctr_encrypt(cipher, initial_counter, ciphertext)
+}
+
+pub fn ecb_encrypt_with_padding(cipher: &impl BlockCipher, plaintext: &[u8], padding: &impl Padding) -> Result<Vec<u8>, Error> {
+    ecb_encrypt(cipher, &padding.pad(cipher.block_size(), plaintext)?)
+}
+
+pub fn ecb_decrypt_with_padding(cipher: &impl BlockCipher, ciphertext: &[u8], padding: &impl Padding) -> Result<Vec<u8>, Error> {
+    let plaintext = ecb_decrypt(cipher, ciphertext)?;
+    Ok(padding.unpad(cipher.block_size(), &plaintext)?)
+}
+
+pub fn cbc_encrypt_with_padding(cipher: &impl BlockCipher, iv: &[u8], plaintext: &[u8], padding: &impl Padding) -> Result<Vec<u8>, Error> {
+    cbc_encrypt(cipher, iv, &padding.pad(cipher.block_size(), plaintext)?)
+}
+
+pub fn cbc_decrypt_with_padding(cipher: &impl BlockCipher, iv: &[u8], ciphertext: &[u8], padding: &impl Padding) -> Result<Vec<u8>, Error> {
+    let plaintext = cbc_decrypt(cipher, iv, ciphertext)?;
+    Ok(padding.unpad(cipher.block_size(), &plaintext)?)
+}
+
+fn aes_128(key: &[u8]) -> Result<Aes, Error> {
+    if key.len() != BLOCK_SIZE_BYTES {
+        return Err(Error::InvalidKeyLength { length: key.len() });
+    }
+    Aes::new(key)
+}
+
+pub fn aes_128_ecb_encrypt_no_padding(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    ecb_encrypt(&aes_128(key)?, plaintext)
+}
+
+pub fn aes_128_ecb_decrypt_no_padding(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    ecb_decrypt(&aes_128(key)?, ciphertext)
+}
+
+pub fn aes_128_ecb_encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    ecb_encrypt_with_padding(&aes_128(key)?, plaintext, &Pkcs7)
+}
+
+pub fn aes_128_cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error>
+{
+    cbc_encrypt_with_padding(&aes_128(key)?, iv, plaintext, &Pkcs7)
+}
+
+pub fn aes_128_cbc_decrypt(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error>
+{
+    cbc_decrypt_with_padding(&aes_128(key)?, iv, ciphertext, &Pkcs7)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::set1::challenge1::base64_to_bytes;
+    use crate::error::PaddingError;
+    use crate::set2::challenge9::{AnsiX923, Iso10126, Iso7816, ZeroPadding};
+    use crate::set1::challenge1::{base64_to_bytes_with_mode, bytes_to_hex, hex_to_bytes, DecodeMode};
     use super::*;
 
 
@@ -105,9 +367,25 @@ mod tests {
         assert_eq!(input_plaintext, result);
     }
 
+    #[test]
+    fn test_cbc_decrypt_invalid_padding() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = vec![0u8; BLOCK_SIZE_BYTES];
+        let aes = Aes::new(key).unwrap();
+
+        // encrypt without padding so the decrypted last byte is not a valid padding length
+        let ciphertext = cbc_encrypt(&aes, &iv, "YELLOW SUBMARIN\x00".as_bytes()).unwrap();
+        let result = aes_128_cbc_decrypt(key, &iv, &ciphertext);
+        assert_eq!(Err(Error::Padding(PaddingError::PaddingLengthOutOfRange { value: 0 })), result);
+
+        let ciphertext = cbc_encrypt(&aes, &iv, "YELLOW SUBMAR\x01\x03\x03".as_bytes()).unwrap();
+        let result = aes_128_cbc_decrypt(key, &iv, &ciphertext);
+        assert_eq!(Err(Error::Padding(PaddingError::MismatchedPaddingBytes)), result);
+    }
+
     #[test]
     fn run_challenge10() {
-        let input_ciphertext = base64_to_bytes("CRIwqt4+szDbqkNY+I0qbNXPg1XLaCM5etQ5Bt9DRFV/xIN2k8Go7jtArLIy
+        let input_ciphertext = base64_to_bytes_with_mode("CRIwqt4+szDbqkNY+I0qbNXPg1XLaCM5etQ5Bt9DRFV/xIN2k8Go7jtArLIy
 P605b071DL8C+FPYSHOXPkMMMFPAKm+Nsu0nCBMQVt9mlluHbVE/yl6VaBCj
 NuOGvHZ9WYvt51uR/lklZZ0ObqD5UaC1rupZwCEK4pIWf6JQ4pTyPjyiPtKX
 g54FNQvbVIHeotUG2kHEvHGS/w2Tt4E42xEwVfi29J3yp0O/TcL7aoRZIcJj
@@ -170,7 +448,7 @@ YNP+EyWCyLRJ3EcOYdvVwVb+vIiyzxnRdugB3vNzaNljHG5ypEJQaTLphIQn
 lP02xcBpMNJN69bijVtnASN/TLV5ocYvtnWPTBKu3OyOkcflMaHCEUgHPW0f
 mGfld4i9Tu35zrKvTDzfxkJX7+KJ72d/V+ksNKWvwn/wvMOZsa2EEOfdCidm
 oql027IS5XvSHynQtvFmw0HTk9UXt8HdVNTqcdy/jUFmXpXNP2Wvn8PrU2Dh
-kkIzWhQ5Rxd/vnM2QQr9Cxa2J9GXEV3kGDiZV90+PCDSVGY4VgF8y7GedI1h");
+kkIzWhQ5Rxd/vnM2QQr9Cxa2J9GXEV3kGDiZV90+PCDSVGY4VgF8y7GedI1h", DecodeMode::Lenient).unwrap();
 
         let key = "YELLOW SUBMARINE".as_bytes();
         let iv = vec![0u8; BLOCK_SIZE_BYTES];
@@ -182,4 +460,150 @@ kkIzWhQ5Rxd/vnM2QQr9Cxa2J9GXEV3kGDiZV90+PCDSVGY4VgF8y7GedI1h");
         assert!(plaintext.starts_with("I'm back and I'm ringin' the bell \n"));
     }
 
+
+    // NIST SP 800-38A, Appendix F, first two blocks of each AES-128 example
+    const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const NIST_IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const NIST_COUNTER: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51";
+
+    #[test]
+    fn test_modes_nist_vectors() {
+        let aes = Aes::new(&hex_to_bytes(NIST_KEY).unwrap()).unwrap();
+        let iv = hex_to_bytes(NIST_IV).unwrap();
+        let counter = hex_to_bytes(NIST_COUNTER).unwrap();
+        let plaintext = hex_to_bytes(NIST_PLAINTEXT).unwrap();
+
+        let ciphertext = ecb_encrypt(&aes, &plaintext).unwrap();
+        assert_eq!("3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf", bytes_to_hex(&ciphertext));
+        assert_eq!(plaintext, ecb_decrypt(&aes, &ciphertext).unwrap());
+
+        let ciphertext = cbc_encrypt(&aes, &iv, &plaintext).unwrap();
+        assert_eq!("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2", bytes_to_hex(&ciphertext));
+        assert_eq!(plaintext, cbc_decrypt(&aes, &iv, &ciphertext).unwrap());
+
+        let ciphertext = cfb_encrypt(&aes, &iv, &plaintext).unwrap();
+        assert_eq!("3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b", bytes_to_hex(&ciphertext));
+        assert_eq!(plaintext, cfb_decrypt(&aes, &iv, &ciphertext).unwrap());
+
+        let ciphertext = ofb_encrypt(&aes, &iv, &plaintext).unwrap();
+        assert_eq!("3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825", bytes_to_hex(&ciphertext));
+        assert_eq!(plaintext, ofb_decrypt(&aes, &iv, &ciphertext).unwrap());
+
+        let ciphertext = ctr_encrypt(&aes, &counter, &plaintext).unwrap();
+        assert_eq!("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff", bytes_to_hex(&ciphertext));
+        assert_eq!(plaintext, ctr_decrypt(&aes, &counter, &ciphertext).unwrap());
+    }
+
+    #[test]
+    fn test_modes_with_toy_ciphers() {
+        let plaintext = "Now that the party is jumping!!!".as_bytes(); // 32 bytes
+
+        for block_size in [4, 8] {
+            let cipher = ToyFeistel::new(block_size, "YELLOW SUBMARINE".as_bytes()).unwrap();
+            let iv = vec![0x42u8; block_size];
+
+            let mut block = plaintext[..block_size].to_vec();
+            cipher.encrypt_block(&mut block);
+            assert_ne!(&plaintext[..block_size], &block[..]);
+            cipher.decrypt_block(&mut block);
+            assert_eq!(&plaintext[..block_size], &block[..]);
+
+            assert_eq!(plaintext, ecb_decrypt(&cipher, &ecb_encrypt(&cipher, plaintext).unwrap()).unwrap());
+            assert_eq!(plaintext, cbc_decrypt(&cipher, &iv, &cbc_encrypt(&cipher, &iv, plaintext).unwrap()).unwrap());
+            assert_eq!(plaintext, pcbc_decrypt(&cipher, &iv, &pcbc_encrypt(&cipher, &iv, plaintext).unwrap()).unwrap());
+
+            // the stream modes accept a partial final block
+            let partial = &plaintext[..plaintext.len() - 1];
+            assert_eq!(partial, cfb_decrypt(&cipher, &iv, &cfb_encrypt(&cipher, &iv, partial).unwrap()).unwrap());
+            assert_eq!(partial, ofb_decrypt(&cipher, &iv, &ofb_encrypt(&cipher, &iv, partial).unwrap()).unwrap());
+            assert_eq!(partial, ctr_decrypt(&cipher, &iv, &ctr_encrypt(&cipher, &iv, partial).unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_toy_feistel_rejects_invalid_parameters() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        for block_size in [0, 1, 7] {
+            assert_eq!(Some(Error::UnsupportedBlockSize { block_size }), ToyFeistel::new(block_size, key).err());
+        }
+        assert_eq!(Some(Error::InvalidKeyLength { length: 0 }), ToyFeistel::new(8, &[]).err());
+    }
+
+    #[test]
+    fn test_pcbc_error_propagation() {
+        let cipher = ToyFeistel::new(8, "YELLOW SUBMARINE".as_bytes()).unwrap();
+        let iv = vec![0u8; 8];
+        let plaintext = "Now that the party is jumping!!!".as_bytes();
+
+        // corrupting a block in PCBC garbles every following block, unlike CBC which only affects the next one
+        let mut ciphertext = pcbc_encrypt(&cipher, &iv, plaintext).unwrap();
+        ciphertext[8] ^= 1;
+        let result = pcbc_decrypt(&cipher, &iv, &ciphertext).unwrap();
+        assert_eq!(&plaintext[..8], &result[..8]);
+        for i in 1..4 {
+            assert_ne!(&plaintext[i * 8..(i + 1) * 8], &result[i * 8..(i + 1) * 8]);
+        }
+    }
+
+    #[test]
+    fn test_modes_with_padding_schemes() {
+        let aes = Aes::new("YELLOW SUBMARINE".as_bytes()).unwrap();
+        let toy = ToyFeistel::new(8, "YELLOW SUBMARINE".as_bytes()).unwrap();
+        let plaintext = "Burning 'em, if you ain't quick and nimble".as_bytes();
+
+        fn round_trip(cipher: &impl BlockCipher, padding: &impl Padding, plaintext: &[u8]) {
+            let iv = vec![0x42u8; cipher.block_size()];
+
+            let ciphertext = ecb_encrypt_with_padding(cipher, plaintext, padding).unwrap();
+            assert_eq!(plaintext, ecb_decrypt_with_padding(cipher, &ciphertext, padding).unwrap());
+
+            let ciphertext = cbc_encrypt_with_padding(cipher, &iv, plaintext, padding).unwrap();
+            assert_eq!(plaintext, cbc_decrypt_with_padding(cipher, &iv, &ciphertext, padding).unwrap());
+        }
+
+        round_trip(&aes, &Pkcs7, plaintext);
+        round_trip(&aes, &AnsiX923, plaintext);
+        round_trip(&aes, &Iso7816, plaintext);
+        round_trip(&aes, &Iso10126, plaintext);
+        round_trip(&aes, &ZeroPadding, plaintext);
+        round_trip(&toy, &Iso7816, plaintext);
+
+        // a PKCS#7 padded message does not validate as ANSI X.923
+        let ciphertext = cbc_encrypt_with_padding(&aes, &[0u8; 16], plaintext, &Pkcs7).unwrap();
+        let result = cbc_decrypt_with_padding(&aes, &[0u8; 16], &ciphertext, &AnsiX923);
+        assert_eq!(Err(Error::Padding(PaddingError::MismatchedPaddingBytes)), result);
+    }
+
+    #[test]
+    fn test_mode_length_errors() {
+        let cipher = ToyFeistel::new(8, "YELLOW SUBMARINE".as_bytes()).unwrap();
+
+        assert_eq!(Err(Error::InvalidBlockLength { length: 7, block_size: 8 }), ecb_encrypt(&cipher, &[0u8; 7]));
+        assert_eq!(Err(Error::InvalidIvLength { length: 16, block_size: 8 }), cbc_encrypt(&cipher, &[0u8; 16], &[0u8; 8]));
+        assert_eq!(Err(Error::InvalidKeyLength { length: 15 }), aes_128_cbc_encrypt(&[0u8; 15], &[0u8; 16], &[0u8; 8]));
+    }
+
+    #[test]
+    fn test_increment_counter_block() {
+        let mut counter = vec![0x00, 0xff, 0xff];
+        increment_counter_block(&mut counter);
+        assert_eq!(vec![0x01, 0x00, 0x00], counter);
+
+        let mut counter = vec![0xff, 0xff];
+        increment_counter_block(&mut counter);
+        assert_eq!(vec![0x00, 0x00], counter);
+    }
+
+    #[test]
+    fn test_add_to_counter_block() {
+        let mut counter = vec![0x00, 0x01, 0xff, 0xfe];
+        add_to_counter_block(&mut counter, 0x0103);
+        assert_eq!(vec![0x00, 0x02, 0x01, 0x01], counter);
+
+        let mut counter = vec![0xff, 0xff];
+        add_to_counter_block(&mut counter, u64::MAX);
+        assert_eq!(vec![0xff, 0xfe], counter);
+    }
+
 }
\ No newline at end of file
@@ -1,14 +1,166 @@
+use std::ops::RangeInclusive;
 
+use rand::{Rng, RngCore};
+
+use crate::set1::challenge7::AES_BLOCK_SIZE;
+use crate::set1::challenge8::count_repeated_blocks;
+use crate::set2::challenge10::{aes_128_cbc_encrypt, aes_128_ecb_encrypt};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMode {
+    Ecb,
+    Cbc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub mode: BlockMode,
+    pub repeated_blocks: usize,
+    // Probability that the guess is right, assuming the plaintext contains repeated aligned blocks
+    // (which is what the chosen input to the oracle guarantees) whenever the ciphertext is long enough for them.
+    pub confidence: f64,
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+pub fn random_aes_key() -> Vec<u8> {
+    random_bytes(AES_BLOCK_SIZE)
+}
+
+// Black box that encrypts under a fresh random key using ECB or CBC chosen at random,
+// after surrounding the input with a random number of random bytes.
+// The mode used for each call is recorded so the detection can be checked afterwards.
+pub struct EncryptionOracle {
+    padding_range: RangeInclusive<usize>,
+    history: Vec<BlockMode>,
+}
+
+impl EncryptionOracle {
+    pub fn new() -> EncryptionOracle {
+        EncryptionOracle::with_padding_range(5..=10)
+    }
+
+    pub fn with_padding_range(padding_range: RangeInclusive<usize>) -> EncryptionOracle {
+        EncryptionOracle { padding_range, history: Vec::new() }
+    }
+
+    pub fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+
+        let prefix = random_bytes(rng.gen_range(self.padding_range.clone()));
+        let suffix = random_bytes(rng.gen_range(self.padding_range.clone()));
+        let plaintext = [&prefix, input, &suffix].concat();
+
+        let key = random_aes_key();
+        let mode = if rng.gen_bool(0.5) { BlockMode::Ecb } else { BlockMode::Cbc };
+        self.history.push(mode);
+
+        match mode {
+            BlockMode::Ecb => aes_128_ecb_encrypt(&key, &plaintext).unwrap(),
+            BlockMode::Cbc => aes_128_cbc_encrypt(&key, &random_bytes(AES_BLOCK_SIZE), &plaintext).unwrap(),
+        }
+    }
+
+    pub fn last_mode(&self) -> Option<BlockMode> {
+        self.history.last().copied()
+    }
+
+    pub fn history(&self) -> &[BlockMode] {
+        &self.history
+    }
+}
+
+impl Default for EncryptionOracle {
+    fn default() -> Self {
+        EncryptionOracle::new()
+    }
+}
+
+// Guess the mode from repeated ciphertext blocks, as in challenge8.
+// A repetition in CBC output only happens by a birthday collision, which bounds the chance of a wrong ECB guess.
+// The absence of repetition only points to CBC when the ciphertext is long enough to hold the detection input,
+// a shorter ciphertext has no repeatable structure and the CBC guess is no better than a coin flip.
+pub fn detect_mode(ciphertext: &[u8], block_size: usize) -> Detection {
+    let repeated_blocks = count_repeated_blocks(ciphertext, block_size);
+    if repeated_blocks == 0 {
+        let confidence = if ciphertext.len() >= detection_input(block_size).len() { 1.0 } else { 0.5 };
+        return Detection { mode: BlockMode::Cbc, repeated_blocks, confidence };
+    }
+
+    let num_blocks = ciphertext.len().div_ceil(block_size) as f64;
+    let num_pairs = num_blocks * (num_blocks - 1.0) / 2.0;
+    let collision_probability = (num_pairs / 2f64.powi(8 * block_size as i32)).min(1.0);
+
+    Detection { mode: BlockMode::Ecb, repeated_blocks, confidence: 1.0 - collision_probability }
+}
+
+// Input that guarantees repeated aligned blocks whatever the random prefix length:
+// a whole block to absorb the prefix followed by two identical blocks.
+pub fn detection_input(block_size: usize) -> Vec<u8> {
+    vec![b'A'; 3 * block_size + block_size - 1]
+}
+
+// Run the detection against the oracle for the given number of trials and return the fraction guessed right.
+pub fn measure_detection_accuracy(oracle: &mut EncryptionOracle, trials: usize) -> f64 {
+    let input = detection_input(AES_BLOCK_SIZE);
+
+    let correct = (0..trials)
+        .filter(|_| {
+            let ciphertext = oracle.encrypt(&input);
+            detect_mode(&ciphertext, AES_BLOCK_SIZE).mode == oracle.last_mode().unwrap()
+        })
+        .count();
+
+    correct as f64 / trials as f64
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_detect_mode() {
+        let key = random_aes_key();
+        let plaintext = detection_input(AES_BLOCK_SIZE);
+
+        let detection = detect_mode(&aes_128_ecb_encrypt(&key, &plaintext).unwrap(), AES_BLOCK_SIZE);
+        assert_eq!(BlockMode::Ecb, detection.mode);
+        assert_eq!(2, detection.repeated_blocks);
+        assert!(detection.confidence > 0.999);
+
+        let detection = detect_mode(&aes_128_cbc_encrypt(&key, &[0u8; 16], &plaintext).unwrap(), AES_BLOCK_SIZE);
+        assert_eq!(BlockMode::Cbc, detection.mode);
+        assert_eq!(1.0, detection.confidence);
+
+        // a single block cannot repeat, so the CBC guess carries no information
+        let detection = detect_mode(&aes_128_cbc_encrypt(&key, &[0u8; 16], b"short").unwrap(), AES_BLOCK_SIZE);
+        assert_eq!(BlockMode::Cbc, detection.mode);
+        assert_eq!(0.5, detection.confidence);
+
+        // with tiny blocks a repetition could be a chance collision, so the confidence drops
+        let detection = detect_mode(&[1, 2, 1, 2, 3, 4, 5, 6, 7, 8], 2);
+        assert_eq!(BlockMode::Ecb, detection.mode);
+        assert!(detection.confidence < 0.9999);
+    }
 
     #[test]
     fn run_challenge11() {
+        let mut oracle = EncryptionOracle::new();
+        let trials = 1000;
+
+        let accuracy = measure_detection_accuracy(&mut oracle, trials);
+        println!("accuracy over {} trials = {}", trials, accuracy);
 
+        assert_eq!(trials, oracle.history().len());
+        assert!(oracle.history().contains(&BlockMode::Ecb) && oracle.history().contains(&BlockMode::Cbc));
+        assert_eq!(1.0, accuracy);
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,184 @@
+use std::cell::Cell;
 
+use crate::error::Error;
+use crate::set1::challenge1::{base64_to_bytes_with_mode, DecodeMode};
+use crate::set1::challenge8::count_repeated_blocks;
+use crate::set2::challenge10::aes_128_ecb_encrypt;
+use crate::set2::challenge11::random_aes_key;
+
+
+const SECRET_BASE64: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkg
+aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq
+dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg
+YnkK";
+
+// The unknown string appended by the challenge12 and challenge14 oracles.
+pub fn challenge12_secret() -> Vec<u8> {
+    base64_to_bytes_with_mode(SECRET_BASE64, DecodeMode::Lenient).unwrap()
+}
+
+// Encrypts prefix || input || secret under a fixed unknown key using ECB, the prefix is empty for challenge12.
+pub struct EcbSecretOracle {
+    key: Vec<u8>,
+    prefix: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl EcbSecretOracle {
+    pub fn new(prefix: &[u8], secret: &[u8]) -> EcbSecretOracle {
+        EcbSecretOracle { key: random_aes_key(), prefix: prefix.to_vec(), secret: secret.to_vec() }
+    }
+
+    pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        aes_128_ecb_encrypt(&self.key, &[&self.prefix, input, &self.secret].concat()).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteAtATimeResult {
+    pub secret: Vec<u8>,
+    pub block_size: usize,
+    pub prefix_len: usize,
+    pub queries: usize,
+}
+
+// Wraps an oracle to count how many times it is queried.
+struct CountingOracle<F: Fn(&[u8]) -> Vec<u8>> {
+    oracle: F,
+    queries: Cell<usize>,
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> CountingOracle<F> {
+    fn query(&self, input: &[u8]) -> Vec<u8> {
+        self.queries.set(self.queries.get() + 1);
+        (self.oracle)(input)
+    }
+}
+
+// Feed growing inputs until the ciphertext grows by a block.
+// Returns the block size and the input length that triggered the new block, which reveals the length of prefix || secret.
+fn detect_block_size<F: Fn(&[u8]) -> Vec<u8>>(oracle: &CountingOracle<F>) -> Result<(usize, usize), Error> {
+    let initial_len = oracle.query(&[]).len();
+    for input_len in 1..=256 {
+        let len = oracle.query(&vec![b'A'; input_len]).len();
+        if len > initial_len {
+            return Ok((len - initial_len, input_len));
+        }
+    }
+    Err(Error::AttackFailed("ciphertext length never changed".to_owned()))
+}
+
+// Find the first two consecutive identical blocks when inserting 2 blocks of marker bytes after a growing filler.
+// Prefix bytes equal to the marker make this underestimate and secret bytes equal to the marker make it overestimate.
+fn find_prefix_len_with_marker<F: Fn(&[u8]) -> Vec<u8>>(oracle: &CountingOracle<F>, block_size: usize, marker: u8) -> Option<usize> {
+    for filler_len in 0..block_size {
+        let ciphertext = oracle.query(&vec![marker; filler_len + 2 * block_size]);
+        let blocks: Vec<&[u8]> = ciphertext.chunks(block_size).collect();
+        if let Some(i) = (0..blocks.len() - 1).find(|&i| blocks[i] == blocks[i + 1]) {
+            return (i * block_size).checked_sub(filler_len);
+        }
+    }
+    None
+}
+
+// Each marker can only be thrown off by the last byte of the prefix or the first byte of the secret,
+// so the median over three markers is always right.
+fn find_prefix_len<F: Fn(&[u8]) -> Vec<u8>>(oracle: &CountingOracle<F>, block_size: usize) -> Result<usize, Error> {
+    let mut estimates = [0u8, 1, 2]
+        .map(|marker| find_prefix_len_with_marker(oracle, block_size, marker))
+        .into_iter()
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(|| Error::AttackFailed("no repeated blocks found".to_owned()))?;
+    estimates.sort();
+    Ok(estimates[1])
+}
+
+// Recover the secret appended by an ECB oracle one byte at a time.
+// Works with or without an unknown constant prefix (challenge14).
+pub fn byte_at_a_time_ecb_decrypt(oracle: impl Fn(&[u8]) -> Vec<u8>) -> Result<ByteAtATimeResult, Error> {
+    let oracle = CountingOracle { oracle, queries: Cell::new(0) };
+
+    let (block_size, input_len_for_new_block) = detect_block_size(&oracle)?;
+
+    // identical blocks of chosen input must encrypt to identical blocks
+    if count_repeated_blocks(&oracle.query(&vec![b'A'; 4 * block_size]), block_size) == 0 {
+        return Err(Error::AttackFailed("oracle is not using ECB mode".to_owned()));
+    }
+
+    let prefix_len = find_prefix_len(&oracle, block_size)?;
+    let initial_len = oracle.query(&[]).len();
+    let secret_len = initial_len
+        .checked_sub(input_len_for_new_block)
+        .and_then(|len| len.checked_sub(prefix_len))
+        .ok_or_else(|| Error::AttackFailed("prefix length exceeds the ciphertext length".to_owned()))?;
+
+    // filler bytes that complete the last prefix block, the attack then works on the following blocks
+    let alignment = vec![b'A'; (block_size - prefix_len % block_size) % block_size];
+    let first_block = (prefix_len + alignment.len()) / block_size;
+
+    // ciphertexts for each possible shift, the byte being recovered sits at the end of a block
+    let shifted_ciphertexts: Vec<Vec<u8>> = (0..block_size)
+        .map(|shift| oracle.query(&[&alignment[..], &vec![b'A'; block_size - 1 - shift]].concat()))
+        .collect();
+
+    let mut known = vec![b'A'; block_size - 1];
+    for position in 0..secret_len {
+        let block_index = first_block + position / block_size;
+        let target = &shifted_ciphertexts[position % block_size][block_index * block_size..(block_index + 1) * block_size];
+
+        // a dictionary of all 256 candidate blocks, encrypted in a single query
+        let window = &known[known.len() - (block_size - 1)..];
+        let candidates: Vec<u8> = (0..=255u8)
+            .flat_map(|b| window.iter().copied().chain(std::iter::once(b)))
+            .collect();
+        let dictionary = oracle.query(&[&alignment[..], &candidates].concat());
+
+        let found = (0..=255u8).find(|&b| {
+            let start = (first_block + b as usize) * block_size;
+            &dictionary[start..start + block_size] == target
+        });
+
+        match found {
+            Some(b) => known.push(b),
+            None => return Err(Error::AttackFailed(format!("no match for secret byte {}", position))),
+        }
+    }
+
+    Ok(ByteAtATimeResult {
+        secret: known[block_size - 1..].to_vec(),
+        block_size,
+        prefix_len,
+        queries: oracle.queries.get(),
+    })
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set2::challenge10::aes_128_cbc_encrypt;
+    use super::*;
+
+    #[test]
+    fn test_not_ecb() {
+        let key = random_aes_key();
+        let result = byte_at_a_time_ecb_decrypt(|input| aes_128_cbc_encrypt(&key, &[0u8; 16], input).unwrap());
+        assert_eq!(Err(Error::AttackFailed("oracle is not using ECB mode".to_owned())), result);
+    }
 
     #[test]
     fn run_challenge12() {
+        let secret = challenge12_secret();
+        let oracle = EcbSecretOracle::new(&[], &secret);
+
+        let result = byte_at_a_time_ecb_decrypt(|input| oracle.encrypt(input)).unwrap();
+        let plaintext = String::from_utf8(result.secret.clone()).unwrap();
+        println!("queries = {}, plaintext = {}", result.queries, plaintext);
 
+        assert_eq!(16, result.block_size);
+        assert_eq!(0, result.prefix_len);
+        assert_eq!(secret, result.secret);
+        assert!(plaintext.starts_with("Rollin' in my 5.0\n"));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,176 @@
+use crate::error::Error;
+use crate::set1::challenge7::{aes_128_ecb_decrypt, AES_BLOCK_SIZE};
+use crate::set2::challenge9::pad_using_pkcs7;
+use crate::set2::challenge10::aes_128_ecb_encrypt;
+use crate::set2::challenge11::random_aes_key;
 
 
+// Key value pairs in insertion order, as found in a structured cookie like foo=bar&baz=qux
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyValueMap {
+    entries: Vec<(String, String)>,
+}
+
+impl KeyValueMap {
+    pub fn new() -> KeyValueMap {
+        KeyValueMap::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    // Replace the value of an existing key in place, otherwise append the pair.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.to_owned(),
+            None => self.entries.push((key.to_owned(), value.to_owned())),
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+}
+
+// Percent encode the metacharacters so user data cannot introduce new pairs.
+pub fn escape_value(value: &str) -> String {
+    value.replace('%', "%25").replace('&', "%26").replace('=', "%3D")
+}
+
+pub fn unescape_value(value: &str) -> String {
+    value.replace("%3D", "=").replace("%26", "&").replace("%25", "%")
+}
+
+pub fn parse_key_value(input: &str) -> Result<KeyValueMap, Error> {
+    let mut map = KeyValueMap::new();
+    if input.is_empty() {
+        return Ok(map);
+    }
+
+    for pair in input.split('&') {
+        match pair.split_once('=') {
+            Some((key, value)) if !key.is_empty() => map.insert(&unescape_value(key), &unescape_value(value)),
+            _ => return Err(Error::InvalidFormat(format!("expected key=value, found {:?}", pair))),
+        }
+    }
+
+    Ok(map)
+}
+
+pub fn encode_key_value(map: &KeyValueMap) -> String {
+    map.entries().iter()
+        .map(|(k, v)| format!("{}={}", escape_value(k), escape_value(v)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+pub fn profile_for(email: &str) -> String {
+    let mut profile = KeyValueMap::new();
+    profile.insert("email", email);
+    profile.insert("uid", "10");
+    profile.insert("role", "user");
+    encode_key_value(&profile)
+}
+
+// Hands out encrypted profiles for any email address under a key unknown to the user.
+pub struct ProfileService {
+    key: Vec<u8>,
+}
+
+impl ProfileService {
+    pub fn new() -> ProfileService {
+        ProfileService { key: random_aes_key() }
+    }
+
+    pub fn encrypted_profile_for(&self, email: &str) -> Vec<u8> {
+        aes_128_ecb_encrypt(&self.key, profile_for(email).as_bytes()).unwrap()
+    }
+
+    pub fn decrypt_profile(&self, ciphertext: &[u8]) -> Result<KeyValueMap, Error> {
+        let plaintext = aes_128_ecb_decrypt(&self.key, ciphertext)?;
+        let plaintext = String::from_utf8(plaintext)
+            .map_err(|_| Error::InvalidFormat("profile is not valid utf-8".to_owned()))?;
+        parse_key_value(&plaintext)
+    }
+}
+
+impl Default for ProfileService {
+    fn default() -> Self {
+        ProfileService::new()
+    }
+}
+
+// Cut and paste ECB blocks from two encrypted profiles into a profile with role=admin.
+// Only relies on the layout email=<email>&uid=10&role=user, the oracle is profile_for followed by encryption.
+pub fn forge_admin_profile(oracle: impl Fn(&str) -> Vec<u8>) -> Vec<u8> {
+    let email_offset = "email=".len();
+    let filler_len = (AES_BLOCK_SIZE - email_offset % AES_BLOCK_SIZE) % AES_BLOCK_SIZE;
+
+    // 1. an email that puts "admin" followed by valid padding alone in the second block
+    let admin_block = String::from_utf8(pad_using_pkcs7(AES_BLOCK_SIZE as u8, "admin".as_bytes())).unwrap();
+    let email = "A".repeat(filler_len) + &admin_block;
+    let ciphertext = oracle(&email);
+    let admin_block_start = email_offset + filler_len;
+    let admin_ciphertext_block = &ciphertext[admin_block_start..admin_block_start + AES_BLOCK_SIZE];
+
+    // 2. an email long enough to end a block right after "role=", with an extra block to fit a realistic address
+    let fixed_len = "email=".len() + "&uid=10&role=".len();
+    let email_len = (AES_BLOCK_SIZE - fixed_len % AES_BLOCK_SIZE) % AES_BLOCK_SIZE + AES_BLOCK_SIZE;
+    let email = "a".repeat(email_len - "@bar.com".len()) + "@bar.com";
+    let ciphertext = oracle(&email);
+    let head = &ciphertext[..fixed_len + email_len];
+
+    // 3. email=...&uid=10&role= | admin<padding>
+    [head, admin_ciphertext_block].concat()
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_parse_key_value() {
+        let map = parse_key_value("foo=bar&baz=qux&zap=zazzle").unwrap();
+        assert_eq!(vec![
+            ("foo".to_owned(), "bar".to_owned()),
+            ("baz".to_owned(), "qux".to_owned()),
+            ("zap".to_owned(), "zazzle".to_owned()),
+        ], map.entries());
+        assert_eq!(Some("qux"), map.get("baz"));
+        assert_eq!("foo=bar&baz=qux&zap=zazzle", encode_key_value(&map));
+
+        assert_eq!(Ok(KeyValueMap::new()), parse_key_value(""));
+        assert!(parse_key_value("foo=bar&baz").is_err());
+        assert!(parse_key_value("=bar").is_err());
+    }
+
+    #[test]
+    fn test_profile_for_escapes_metacharacters() {
+        let profile = profile_for("foo@bar.com&role=admin");
+        assert_eq!("email=foo@bar.com%26role%3Dadmin&uid=10&role=user", profile);
+
+        let map = parse_key_value(&profile).unwrap();
+        assert_eq!(Some("foo@bar.com&role=admin"), map.get("email"));
+        assert_eq!(Some("user"), map.get("role"));
+        assert_eq!(3, map.entries().len());
+    }
 
     #[test]
     fn run_challenge13() {
+        let service = ProfileService::new();
+
+        let profile = service.decrypt_profile(&service.encrypted_profile_for("foo@bar.com")).unwrap();
+        assert_eq!(Some("user"), profile.get("role"));
+
+        let forged = forge_admin_profile(|email| service.encrypted_profile_for(email));
+        let profile = service.decrypt_profile(&forged).unwrap();
+        println!("forged profile = {}", encode_key_value(&profile));
 
+        assert_eq!(Some("admin"), profile.get("role"));
+        assert_eq!(Some("10"), profile.get("uid"));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,47 @@
+use rand::Rng;
 
+use crate::set2::challenge11::random_bytes;
+use crate::set2::challenge12::EcbSecretOracle;
+
+
+// Challenge14 oracle, a random count of random bytes is fixed at creation and prepended to every input.
+pub fn random_prefix_oracle(secret: &[u8]) -> EcbSecretOracle {
+    let prefix = random_bytes(rand::thread_rng().gen_range(0..=64));
+    EcbSecretOracle::new(&prefix, secret)
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set2::challenge12::{byte_at_a_time_ecb_decrypt, challenge12_secret};
+    use super::*;
+
+    #[test]
+    fn test_prefix_ending_with_marker_bytes() {
+        let secret = challenge12_secret();
+
+        // prefix and secret made of the bytes the prefix detection inserts
+        for prefix in [vec![0u8; 7], vec![1u8; 16], vec![9, 9, 2]] {
+            let oracle = EcbSecretOracle::new(&prefix, &[&[0u8, 0, 1][..], &secret].concat());
+            let result = byte_at_a_time_ecb_decrypt(|input| oracle.encrypt(input)).unwrap();
+
+            assert_eq!(prefix.len(), result.prefix_len);
+            assert!(result.secret.ends_with(&secret));
+        }
+    }
 
     #[test]
     fn run_challenge14() {
+        let secret = challenge12_secret();
+
+        for _ in 0..5 {
+            let oracle = random_prefix_oracle(&secret);
+            let result = byte_at_a_time_ecb_decrypt(|input| oracle.encrypt(input)).unwrap();
+            println!("prefix_len = {}, queries = {}", result.prefix_len, result.queries);
 
+            assert_eq!(secret, result.secret);
+        }
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,23 @@
 
 
-
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::error::PaddingError;
+    use crate::set2::challenge9::unpad_pkcs7;
 
     #[test]
     fn run_challenge15() {
+        let block_size: u8 = 16;
+
+        let result = unpad_pkcs7(block_size, "ICE ICE BABY\x04\x04\x04\x04".as_bytes());
+        assert_eq!(Ok("ICE ICE BABY".as_bytes().to_vec()), result);
+
+        let result = unpad_pkcs7(block_size, "ICE ICE BABY\x05\x05\x05\x05".as_bytes());
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), result);
 
+        let result = unpad_pkcs7(block_size, "ICE ICE BABY\x01\x02\x03\x04".as_bytes());
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), result);
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,164 @@
+use crate::error::Error;
+use crate::set1::challenge2::fixed_xor;
+use crate::set1::challenge7::AES_BLOCK_SIZE;
+use crate::set2::challenge10::{aes_128_cbc_decrypt, aes_128_cbc_encrypt};
+use crate::set2::challenge11::{random_aes_key, random_bytes};
 
 
+pub const COMMENT_PREFIX: &str = "comment1=cooking%20MCs;userdata=";
+pub const COMMENT_SUFFIX: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
+pub const ADMIN_MARKER: &str = ";admin=true;";
+
+// Quote out the metacharacters so user data cannot introduce new fields.
+pub fn quote_user_data(user_data: &str) -> String {
+    user_data.replace(';', "%3B").replace('=', "%3D")
+}
+
+// Cipher used by the comment service, so the same service and attack can be run against several modes.
+pub trait CommentCipher {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8>;
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+// AES-128 CBC under a key and IV unknown to the user.
+pub struct CbcCommentCipher {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl CbcCommentCipher {
+    pub fn new() -> CbcCommentCipher {
+        CbcCommentCipher { key: random_aes_key(), iv: random_bytes(AES_BLOCK_SIZE) }
+    }
+}
+
+impl Default for CbcCommentCipher {
+    fn default() -> Self {
+        CbcCommentCipher::new()
+    }
+}
+
+impl CommentCipher for CbcCommentCipher {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        aes_128_cbc_encrypt(&self.key, &self.iv, plaintext).unwrap()
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        aes_128_cbc_decrypt(&self.key, &self.iv, ciphertext)
+    }
+}
+
+// Wraps user data in fixed comment fields and encrypts it, CBC unless another cipher is given.
+pub struct CommentService<C: CommentCipher = CbcCommentCipher> {
+    cipher: C,
+}
+
+impl CommentService {
+    pub fn new() -> CommentService {
+        CommentService { cipher: CbcCommentCipher::new() }
+    }
+}
+
+impl<C: CommentCipher> CommentService<C> {
+    pub fn with_cipher(cipher: C) -> CommentService<C> {
+        CommentService { cipher }
+    }
+
+    pub fn encrypt(&self, user_data: &str) -> Vec<u8> {
+        let plaintext = [COMMENT_PREFIX, &quote_user_data(user_data), COMMENT_SUFFIX].concat();
+        self.cipher.encrypt(plaintext.as_bytes())
+    }
+
+    // The plaintext is not required to be valid text, a flipped block decrypts to random bytes.
+    pub fn is_admin(&self, ciphertext: &[u8]) -> Result<bool, Error> {
+        let plaintext = self.cipher.decrypt(ciphertext)?;
+        Ok(plaintext.windows(ADMIN_MARKER.len()).any(|w| w == ADMIN_MARKER.as_bytes()))
+    }
+}
+
+impl Default for CommentService {
+    fn default() -> Self {
+        CommentService::new()
+    }
+}
+
+// XOR mask that turns the known plaintext into the target when applied to the preceding ciphertext block.
+pub fn cbc_bitflip_mask(known_plaintext: &[u8], target: &[u8]) -> Vec<u8> {
+    fixed_xor(known_plaintext, target)
+}
+
+// Rewrite the plaintext bytes at the given offset from known_plaintext to target by flipping the ciphertext one block earlier.
+// The block before the edited bytes decrypts to garbage, so the offset must be at least one block in.
+pub fn cbc_bitflip(ciphertext: &mut [u8], block_size: usize, offset: usize, known_plaintext: &[u8], target: &[u8]) -> Result<(), Error> {
+    if known_plaintext.len() != target.len() {
+        return Err(Error::LengthMismatch { expected: known_plaintext.len(), actual: target.len() });
+    }
+    if offset < block_size {
+        return Err(Error::AttackFailed("target must lie after the first block of the ciphertext".to_owned()));
+    }
+    if offset.checked_add(target.len()).is_none_or(|end| end > ciphertext.len()) {
+        return Err(Error::OutOfBounds { offset, length: target.len(), size: ciphertext.len() });
+    }
+
+    let mask = cbc_bitflip_mask(known_plaintext, target);
+    for (c, m) in ciphertext[offset - block_size..].iter_mut().zip(mask) {
+        *c ^= m;
+    }
+    Ok(())
+}
+
+// Submit a block to sacrifice followed by a block of known bytes, then flip the known bytes into the admin marker.
+// The flip takes the plaintext offset of the known bytes, which lets the same harness drive CBC and CTR.
+pub fn forge_admin_comment(
+    oracle: impl Fn(&str) -> Vec<u8>,
+    flip: impl Fn(&mut [u8], usize, &[u8], &[u8]) -> Result<(), Error>,
+) -> Result<Vec<u8>, Error> {
+    let known = "A".repeat(ADMIN_MARKER.len());
+    let user_data = "A".repeat(AES_BLOCK_SIZE) + &known;
+    let mut ciphertext = oracle(&user_data);
+
+    // the prefix fills exactly two blocks so the user data is block aligned
+    let offset = COMMENT_PREFIX.len() + AES_BLOCK_SIZE;
+    flip(&mut ciphertext, offset, known.as_bytes(), ADMIN_MARKER.as_bytes())?;
+    Ok(ciphertext)
+}
+
+pub fn cbc_flip(ciphertext: &mut [u8], offset: usize, known_plaintext: &[u8], target: &[u8]) -> Result<(), Error> {
+    cbc_bitflip(ciphertext, AES_BLOCK_SIZE, offset, known_plaintext, target)
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_quoting_prevents_injection() {
+        let service = CommentService::new();
+        assert_eq!("%3Badmin%3Dtrue%3B", quote_user_data(ADMIN_MARKER));
+
+        let ciphertext = service.encrypt(ADMIN_MARKER);
+        assert_eq!(Ok(false), service.is_admin(&ciphertext));
+    }
+
+    #[test]
+    fn test_cbc_bitflip_bounds() {
+        let mut ciphertext = vec![0u8; 32];
+        assert!(matches!(cbc_bitflip(&mut ciphertext, 16, 8, b"A", b"B"), Err(Error::AttackFailed(_))));
+        assert_eq!(Err(Error::OutOfBounds { offset: 31, length: 2, size: 32 }), cbc_bitflip(&mut ciphertext, 16, 31, b"AB", b"BA"));
+        assert_eq!(Err(Error::OutOfBounds { offset: usize::MAX, length: 2, size: 32 }), cbc_bitflip(&mut ciphertext, 16, usize::MAX, b"AB", b"BA"));
+        assert_eq!(Err(Error::LengthMismatch { expected: 2, actual: 1 }), cbc_bitflip(&mut ciphertext, 16, 17, b"AB", b"B"));
+        assert_eq!(Ok(()), cbc_bitflip(&mut ciphertext, 16, 17, b"A", b"B"));
+        assert_eq!(b'A' ^ b'B', ciphertext[1]);
+    }
 
     #[test]
     fn run_challenge16() {
+        let service = CommentService::new();
 
+        let forged = forge_admin_comment(|user_data| service.encrypt(user_data), cbc_flip).unwrap();
+        assert_eq!(Ok(true), service.is_admin(&forged));
     }
 
-}
\ No newline at end of file
+}
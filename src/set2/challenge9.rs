@@ -1,20 +1,185 @@
+use rand::RngCore;
+
+use crate::error::PaddingError;
 
 
 pub fn pad_using_pkcs7(block_size: u8, input: &[u8]) -> Vec<u8> {
-    if block_size <= 0 || input.len() <= 0 {
-        panic!("block_size and input must be greater than 0");
+    if block_size == 0 {
+        panic!("block_size must be greater than 0");
     }
 
-    let remainder = block_size - (input.len() % block_size as usize) as u8;
-
-    let padding = match remainder {
-        0 => vec![block_size; block_size as usize],
-        _ => vec![remainder; remainder as usize]
-    };
+    // always pad, so an input that fills the last block (including empty input) gets a full block of padding
+    let num_padding_bytes = block_size - (input.len() % block_size as usize) as u8;
+    let padding = vec![num_padding_bytes; num_padding_bytes as usize];
 
     [input, &padding].concat()
 }
 
+pub fn unpad_pkcs7(block_size: u8, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    // no input can be a whole number of empty blocks
+    if block_size == 0 || input.is_empty() || !input.len().is_multiple_of(block_size as usize) {
+        return Err(PaddingError::InvalidLength { length: input.len(), block_size: block_size as usize });
+    }
+
+    // the last byte holds the number of padding bytes, which must be between 1 and block_size
+    let num_padding_bytes = input[input.len() - 1];
+    if num_padding_bytes == 0 || num_padding_bytes > block_size {
+        return Err(PaddingError::PaddingLengthOutOfRange { value: num_padding_bytes });
+    }
+
+    // every padding byte must hold the same value
+    let (unpadded, padding) = input.split_at(input.len() - num_padding_bytes as usize);
+    if padding.iter().any(|&b| b != num_padding_bytes) {
+        return Err(PaddingError::MismatchedPaddingBytes);
+    }
+
+    Ok(unpadded.to_vec())
+}
+
+// A block padding scheme, unpad must reject anything that pad could not have produced.
+pub trait Padding {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError>;
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError>;
+}
+
+// n bytes of value n.
+pub struct Pkcs7;
+
+// n - 1 zero bytes followed by a byte of value n.
+pub struct AnsiX923;
+
+// A single 0x80 byte followed by zero bytes.
+pub struct Iso7816;
+
+// n - 1 random bytes followed by a byte of value n.
+pub struct Iso10126;
+
+// Zero bytes up to the block boundary, nothing when the input is already aligned.
+// Trailing zeros in the plaintext are indistinguishable from padding so they are removed as well.
+pub struct ZeroPadding;
+
+// The schemes store the padding length in a single byte, so the block size has to fit in one.
+fn checked_block_size(block_size: usize) -> Result<u8, PaddingError> {
+    match u8::try_from(block_size) {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(PaddingError::UnsupportedBlockSize { block_size }),
+    }
+}
+
+fn num_padding_bytes(block_size: usize, input_len: usize) -> Result<usize, PaddingError> {
+    let block_size = checked_block_size(block_size)? as usize;
+    Ok(block_size - input_len % block_size)
+}
+
+fn check_padded_length(block_size: usize, input: &[u8]) -> Result<(), PaddingError> {
+    checked_block_size(block_size)?;
+    if input.is_empty() || !input.len().is_multiple_of(block_size) {
+        return Err(PaddingError::InvalidLength { length: input.len(), block_size });
+    }
+    Ok(())
+}
+
+// Read the trailing length byte used by ANSI X.923 and ISO 10126, returns the number of padding bytes.
+fn padding_length_byte(block_size: usize, input: &[u8]) -> Result<usize, PaddingError> {
+    check_padded_length(block_size, input)?;
+
+    let value = input[input.len() - 1];
+    if value == 0 || value as usize > block_size {
+        return Err(PaddingError::PaddingLengthOutOfRange { value });
+    }
+    Ok(value as usize)
+}
+
+impl Padding for Pkcs7 {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        Ok(pad_using_pkcs7(checked_block_size(block_size)?, input))
+    }
+
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        unpad_pkcs7(checked_block_size(block_size)?, input)
+    }
+}
+
+impl Padding for AnsiX923 {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let n = num_padding_bytes(block_size, input.len())?;
+        let mut padded = input.to_vec();
+        padded.resize(input.len() + n - 1, 0);
+        padded.push(n as u8);
+        Ok(padded)
+    }
+
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let n = padding_length_byte(block_size, input)?;
+        let (unpadded, padding) = input.split_at(input.len() - n);
+        if padding[..n - 1].iter().any(|&b| b != 0) {
+            return Err(PaddingError::MismatchedPaddingBytes);
+        }
+        Ok(unpadded.to_vec())
+    }
+}
+
+impl Padding for Iso7816 {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let n = num_padding_bytes(block_size, input.len())?;
+        let mut padded = input.to_vec();
+        padded.push(0x80);
+        padded.resize(input.len() + n, 0);
+        Ok(padded)
+    }
+
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        check_padded_length(block_size, input)?;
+
+        // skip the zeros of the last block and expect the 0x80 marker
+        let last_block = &input[input.len() - block_size..];
+        match last_block.iter().rposition(|&b| b != 0) {
+            Some(i) if last_block[i] == 0x80 => Ok(input[..input.len() - block_size + i].to_vec()),
+            _ => Err(PaddingError::MismatchedPaddingBytes),
+        }
+    }
+}
+
+impl Padding for Iso10126 {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let n = num_padding_bytes(block_size, input.len())?;
+        let mut random_fill = vec![0u8; n - 1];
+        rand::thread_rng().fill_bytes(&mut random_fill);
+        Ok([input, &random_fill, &[n as u8]].concat())
+    }
+
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        // the fill is random so only the length byte can be validated
+        let n = padding_length_byte(block_size, input)?;
+        Ok(input[..input.len() - n].to_vec())
+    }
+}
+
+impl Padding for ZeroPadding {
+    fn pad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let n = num_padding_bytes(block_size, input.len())? % block_size;
+        let mut padded = input.to_vec();
+        padded.resize(input.len() + n, 0);
+        Ok(padded)
+    }
+
+    fn unpad(&self, block_size: usize, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        checked_block_size(block_size)?;
+
+        // empty input is left empty by pad
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        check_padded_length(block_size, input)?;
+
+        // padding never spans more than the last block
+        let last_block_start = input.len() - block_size;
+        let end = input[last_block_start..].iter().rposition(|&b| b != 0)
+            .map_or(last_block_start, |i| last_block_start + i + 1);
+        Ok(input[..end].to_vec())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -27,9 +192,81 @@ mod tests {
         let input = "YELLOW SUBMARINE".as_bytes();
         let expected = "YELLOW SUBMARINE\x04\x04\x04\x04".as_bytes();
 
-        let result = pad_using_pkcs7(block_size, &input);
+        let result = pad_using_pkcs7(block_size, input);
 
         assert_eq!(expected, result)
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_pad_full_block() {
+        assert_eq!(vec![4u8; 4], pad_using_pkcs7(4, &[]));
+        assert_eq!("ABCD\x04\x04\x04\x04".as_bytes(), pad_using_pkcs7(4, "ABCD".as_bytes()));
+    }
+
+    #[test]
+    fn test_unpad_pkcs7() {
+        assert_eq!(Ok(Vec::new()), unpad_pkcs7(4, &pad_using_pkcs7(4, &[])));
+        assert_eq!(Ok("ABC".as_bytes().to_vec()), unpad_pkcs7(4, "ABC\x01".as_bytes()));
+
+        assert_eq!(Err(PaddingError::InvalidLength { length: 0, block_size: 4 }), unpad_pkcs7(4, &[]));
+        assert_eq!(Err(PaddingError::InvalidLength { length: 5, block_size: 4 }), unpad_pkcs7(4, "ABCD\x01".as_bytes()));
+        assert_eq!(Err(PaddingError::PaddingLengthOutOfRange { value: 0 }), unpad_pkcs7(4, "ABC\x00".as_bytes()));
+        assert_eq!(Err(PaddingError::PaddingLengthOutOfRange { value: 5 }), unpad_pkcs7(4, "ABCD\x05\x05\x05\x05".as_bytes()));
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), unpad_pkcs7(4, "AB\x01\x02".as_bytes()));
+        assert_eq!(Err(PaddingError::InvalidLength { length: 4, block_size: 0 }), unpad_pkcs7(0, "ABC\x01".as_bytes()));
+    }
+
+
+    #[test]
+    fn test_padding_schemes_round_trip() {
+        let schemes: [&dyn Padding; 5] = [&Pkcs7, &AnsiX923, &Iso7816, &Iso10126, &ZeroPadding];
+
+        for scheme in schemes {
+            for len in 0..=16 {
+                let input: Vec<u8> = (1..=len).collect();
+                let padded = scheme.pad(8, &input).unwrap();
+                assert!(padded.len().is_multiple_of(8));
+                assert_eq!(Ok(input), scheme.unpad(8, &padded));
+            }
+        }
+    }
+
+    #[test]
+    fn test_padding_schemes_layout() {
+        let input = "ABC".as_bytes();
+
+        assert_eq!(Ok("ABC\x05\x05\x05\x05\x05".as_bytes().to_vec()), Pkcs7.pad(8, input));
+        assert_eq!(Ok("ABC\x00\x00\x00\x00\x05".as_bytes().to_vec()), AnsiX923.pad(8, input));
+        assert_eq!(Ok(b"ABC\x80\x00\x00\x00\x00".to_vec()), Iso7816.pad(8, input));
+        assert_eq!(Ok("ABC\x00\x00\x00\x00\x00".as_bytes().to_vec()), ZeroPadding.pad(8, input));
+        assert_eq!(Ok("ABCDEFGH".as_bytes().to_vec()), ZeroPadding.pad(8, "ABCDEFGH".as_bytes()));
+
+        let padded = Iso10126.pad(8, input).unwrap();
+        assert_eq!(8, padded.len());
+        assert_eq!(5, padded[7]);
+    }
+
+    #[test]
+    fn test_padding_schemes_validation() {
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), AnsiX923.unpad(4, "A\x00\x01\x03".as_bytes()));
+        assert_eq!(Err(PaddingError::PaddingLengthOutOfRange { value: 0 }), AnsiX923.unpad(4, "A\x00\x00\x00".as_bytes()));
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), Iso7816.unpad(4, b"AB\x81\x00"));
+        assert_eq!(Err(PaddingError::MismatchedPaddingBytes), Iso7816.unpad(4, b"\x80\x00\x00\x00\x00\x00\x00\x00"));
+        assert_eq!(Err(PaddingError::PaddingLengthOutOfRange { value: 9 }), Iso10126.unpad(4, "ABC\x09".as_bytes()));
+        assert_eq!(Err(PaddingError::InvalidLength { length: 3, block_size: 4 }), ZeroPadding.unpad(4, "ABC".as_bytes()));
+    }
+
+    #[test]
+    fn test_padding_schemes_block_size() {
+        let schemes: [&dyn Padding; 5] = [&Pkcs7, &AnsiX923, &Iso7816, &Iso10126, &ZeroPadding];
+
+        for scheme in schemes {
+            for block_size in [0, 256] {
+                assert_eq!(Err(PaddingError::UnsupportedBlockSize { block_size }), scheme.pad(block_size, "ABC".as_bytes()));
+                assert_eq!(Err(PaddingError::UnsupportedBlockSize { block_size }), scheme.unpad(block_size, &[]));
+                assert_eq!(Err(PaddingError::UnsupportedBlockSize { block_size }), scheme.unpad(block_size, &[1; 512]));
+            }
+        }
+    }
+
+}
@@ -1,8 +1,8 @@
-pub(crate) mod challenge9;
-pub(crate) mod challenge10;
-pub(crate) mod challenge11;
-pub(crate) mod challenge12;
-pub(crate) mod challenge13;
-pub(crate) mod challenge14;
-pub(crate) mod challenge15;
-pub(crate) mod challenge16;
\ No newline at end of file
+pub mod challenge9;
+pub mod challenge10;
+pub mod challenge11;
+pub mod challenge12;
+pub mod challenge13;
+pub mod challenge14;
+pub mod challenge15;
+pub mod challenge16;
\ No newline at end of file
@@ -1,14 +1,221 @@
+use rand::Rng;
 
+use crate::error::Error;
+use crate::set1::challenge1::base64_to_bytes;
+use crate::set1::challenge2::fixed_xor;
+use crate::set1::challenge7::AES_BLOCK_SIZE;
+use crate::set2::challenge9::{pad_using_pkcs7, unpad_pkcs7};
+use crate::set2::challenge10::{aes_128_cbc_decrypt, aes_128_cbc_encrypt};
+use crate::set2::challenge11::{random_aes_key, random_bytes};
+
+
+pub const CHALLENGE17_STRINGS: [&str; 10] = [
+    "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+    "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+    "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+    "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+    "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+    "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+    "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+    "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+    "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+    "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+];
+
+// Server that hands out CBC encrypted strings and leaks whether a submitted ciphertext has valid padding.
+pub struct PaddingOracleServer {
+    key: Vec<u8>,
+}
+
+impl PaddingOracleServer {
+    pub fn new() -> PaddingOracleServer {
+        PaddingOracleServer { key: random_aes_key() }
+    }
+
+    // Returns (iv, ciphertext) for one of the challenge strings picked at random.
+    pub fn encrypt_random_string(&self) -> (Vec<u8>, Vec<u8>) {
+        let index = rand::thread_rng().gen_range(0..CHALLENGE17_STRINGS.len());
+        self.encrypt(&base64_to_bytes(CHALLENGE17_STRINGS[index]).unwrap())
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let iv = random_bytes(AES_BLOCK_SIZE);
+        let ciphertext = aes_128_cbc_encrypt(&self.key, &iv, plaintext).unwrap();
+        (iv, ciphertext)
+    }
+
+    pub fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        aes_128_cbc_decrypt(&self.key, iv, ciphertext)
+    }
+
+    pub fn has_valid_padding(&self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        !matches!(self.decrypt(iv, ciphertext), Err(Error::Padding(_)))
+    }
+}
+
+impl Default for PaddingOracleServer {
+    fn default() -> Self {
+        PaddingOracleServer::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaddingOracleDecryption {
+    pub plaintext: Vec<u8>,
+    pub queries_per_block: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaddingOracleEncryption {
+    pub iv: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub queries_per_block: Vec<usize>,
+}
+
+// Recover D(block), the block cipher output before the CBC xor, by submitting the block
+// with a forged IV and finding the byte values that make the padding valid, last byte first.
+// The forged IV starts from the given previous block, as tampering with a real ciphertext would.
+fn recover_intermediate(oracle: &impl Fn(&[u8], &[u8]) -> bool, previous: &[u8], block: &[u8], queries: &mut usize) -> Result<Vec<u8>, Error> {
+    let block_size = block.len();
+    let mut intermediate = vec![0u8; block_size];
+    let mut forged_iv = previous.to_vec();
+
+    for position in (0..block_size).rev() {
+        let padding_value = (block_size - position) as u8;
+
+        // make the already recovered bytes decrypt to the padding value
+        for i in position + 1..block_size {
+            forged_iv[i] = intermediate[i] ^ padding_value;
+        }
+
+        let mut found = None;
+        for guess in 0..=255u8 {
+            forged_iv[position] = guess;
+            *queries += 1;
+            if !oracle(&forged_iv, block) {
+                continue;
+            }
+
+            // for the last byte a valid padding could also be \x02\x02 (or longer) caused by the byte before,
+            // changing that byte only breaks the padding in that case
+            if position == block_size - 1 && position > 0 {
+                forged_iv[position - 1] ^= 0xff;
+                *queries += 1;
+                let still_valid = oracle(&forged_iv, block);
+                forged_iv[position - 1] ^= 0xff;
+                if !still_valid {
+                    continue;
+                }
+            }
+
+            found = Some(guess);
+            break;
+        }
+
+        match found {
+            Some(guess) => intermediate[position] = guess ^ padding_value,
+            None => return Err(Error::AttackFailed(format!("no valid padding found for byte {}", position))),
+        }
+    }
+
+    Ok(intermediate)
+}
+
+// Decrypt every block of a CBC ciphertext using only a padding oracle taking (iv, ciphertext),
+// the first block is recovered by using the IV as the preceding block.
+pub fn padding_oracle_decrypt(oracle: impl Fn(&[u8], &[u8]) -> bool, iv: &[u8], ciphertext: &[u8]) -> Result<PaddingOracleDecryption, Error> {
+    let block_size = iv.len();
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(block_size) {
+        return Err(Error::InvalidBlockLength { length: ciphertext.len(), block_size });
+    }
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut queries_per_block = Vec::new();
+    let mut previous = iv;
+
+    for block in ciphertext.chunks(block_size) {
+        let mut queries = 0;
+        let intermediate = recover_intermediate(&oracle, previous, block, &mut queries)?;
+        plaintext.extend(fixed_xor(&intermediate, previous));
+        queries_per_block.push(queries);
+        previous = block;
+    }
+
+    Ok(PaddingOracleDecryption {
+        plaintext: unpad_pkcs7(block_size as u8, &plaintext)?,
+        queries_per_block,
+    })
+}
+
+// CBC-R: encrypt any plaintext without the key by working backwards from a random last block,
+// choosing each preceding block so it xors the recovered intermediate into the wanted plaintext.
+pub fn padding_oracle_encrypt(oracle: impl Fn(&[u8], &[u8]) -> bool, plaintext: &[u8], block_size: usize) -> Result<PaddingOracleEncryption, Error> {
+    let padded_plaintext = pad_using_pkcs7(block_size as u8, plaintext);
+
+    let mut blocks = vec![random_bytes(block_size)];
+    let mut queries_per_block = Vec::new();
+
+    for plaintext_block in padded_plaintext.chunks(block_size).rev() {
+        let mut queries = 0;
+        let intermediate = recover_intermediate(&oracle, &vec![0u8; block_size], &blocks[0], &mut queries)?;
+        blocks.insert(0, fixed_xor(&intermediate, plaintext_block));
+        queries_per_block.insert(0, queries);
+    }
+
+    let iv = blocks.remove(0);
+    Ok(PaddingOracleEncryption { iv, ciphertext: blocks.concat(), queries_per_block })
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_false_positive_padding() {
+        let server = PaddingOracleServer::new();
+
+        // the byte before the last one is \x02 so the untouched last byte of the first block already gives
+        // valid padding for two values, \x01 and \x02
+        let plaintext = "AAAAAAAAAAAAAA\x02BCCCCCCCCCCCCC\x02\x01".as_bytes();
+        for _ in 0..5 {
+            let (iv, ciphertext) = server.encrypt(plaintext);
+            let result = padding_oracle_decrypt(|iv, c| server.has_valid_padding(iv, c), &iv, &ciphertext).unwrap();
+            assert_eq!(plaintext, result.plaintext);
+        }
+    }
+
+    #[test]
+    fn test_padding_oracle_encrypt() {
+        let server = PaddingOracleServer::new();
+        let plaintext = "comment1=cooking%20MCs;userdata=;admin=true;".as_bytes();
+
+        let result = padding_oracle_encrypt(|iv, c| server.has_valid_padding(iv, c), plaintext, AES_BLOCK_SIZE).unwrap();
+        println!("queries per block = {:?}", result.queries_per_block);
+
+        assert_eq!(3, result.queries_per_block.len());
+        assert_eq!(Ok(plaintext.to_vec()), server.decrypt(&result.iv, &result.ciphertext));
+    }
 
     #[test]
     fn run_challenge17() {
+        let server = PaddingOracleServer::new();
+
+        for encoded in CHALLENGE17_STRINGS {
+            let expected = base64_to_bytes(encoded).unwrap();
+            let (iv, ciphertext) = server.encrypt(&expected);
+
+            let result = padding_oracle_decrypt(|iv, c| server.has_valid_padding(iv, c), &iv, &ciphertext).unwrap();
+            println!("queries per block = {:?}, plaintext = {}", result.queries_per_block, String::from_utf8_lossy(&result.plaintext));
+
+            assert_eq!(expected, result.plaintext);
+            assert_eq!(ciphertext.len() / AES_BLOCK_SIZE, result.queries_per_block.len());
+        }
 
+        let (iv, ciphertext) = server.encrypt_random_string();
+        let result = padding_oracle_decrypt(|iv, c| server.has_valid_padding(iv, c), &iv, &ciphertext).unwrap();
+        assert!(result.plaintext.starts_with("00000".as_bytes()));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,160 @@
+use crate::error::Error;
+use crate::set1::challenge7::{Aes, AES_BLOCK_SIZE};
+use crate::set2::challenge10::{apply_ctr_keystream, BlockCipher};
 
 
+// How the nonce and the block counter are laid out in the 16 byte counter block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterLayout {
+    // 64-bit nonce followed by a 64-bit little endian block counter, as used by cryptopals.
+    Nonce64Counter64Le,
+    // 96-bit nonce followed by a 32-bit big endian block counter, as used by GCM.
+    Nonce96Counter32Be,
+}
+
+impl CounterLayout {
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            CounterLayout::Nonce64Counter64Le => 8,
+            CounterLayout::Nonce96Counter32Be => 12,
+        }
+    }
+}
+
+// CTR mode keystream over any 16 byte block cipher, the keystream can be generated from any byte offset.
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    nonce: Vec<u8>,
+    layout: CounterLayout,
+    initial_counter: u64,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    pub fn new(cipher: C, nonce: &[u8], layout: CounterLayout) -> Result<Ctr<C>, Error> {
+        if cipher.block_size() != AES_BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize { block_size: cipher.block_size() });
+        }
+        if nonce.len() != layout.nonce_len() {
+            return Err(Error::InvalidNonceLength { length: nonce.len(), expected: layout.nonce_len() });
+        }
+
+        Ok(Ctr { cipher, nonce: nonce.to_vec(), layout, initial_counter: 0 })
+    }
+
+    // Value of the counter for the first block, GCM for instance starts the payload at 2.
+    pub fn with_initial_counter(mut self, initial_counter: u64) -> Ctr<C> {
+        self.initial_counter = initial_counter;
+        self
+    }
+
+    // Counters wrap around at the width of the counter field.
+    pub fn counter_block(&self, block_index: u64) -> Vec<u8> {
+        let counter = self.initial_counter.wrapping_add(block_index);
+        match self.layout {
+            CounterLayout::Nonce64Counter64Le => [&self.nonce[..], &counter.to_le_bytes()].concat(),
+            CounterLayout::Nonce96Counter32Be => [&self.nonce[..], &(counter as u32).to_be_bytes()].concat(),
+        }
+    }
+
+    pub fn keystream_block(&self, block_index: u64) -> Vec<u8> {
+        let mut block = self.counter_block(block_index);
+        self.cipher.encrypt_block(&mut block);
+        block
+    }
+
+    // Keystream bytes starting at any offset, only the blocks covering the range are generated.
+    pub fn keystream(&self, offset: u64, len: usize) -> Vec<u8> {
+        let mut keystream = vec![0u8; len];
+        self.apply_keystream(offset, &mut keystream);
+        keystream
+    }
+
+    // Encrypt or decrypt in place data that sits at the given offset of the stream.
+    pub fn apply_keystream(&self, offset: u64, data: &mut [u8]) {
+        apply_ctr_keystream(&self.cipher, |block_index| self.counter_block(block_index), offset, data);
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut ciphertext = plaintext.to_vec();
+        self.apply_keystream(0, &mut ciphertext);
+        ciphertext
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Vec<u8> {
+        self.encrypt(ciphertext)
+    }
+}
+
+// AES-128 CTR with the cryptopals layout, the nonce is encoded as 64-bit little endian.
+pub fn aes_128_ctr(key: &[u8], nonce: u64, data: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.len() != AES_BLOCK_SIZE {
+        return Err(Error::InvalidKeyLength { length: key.len() });
+    }
+
+    let ctr = Ctr::new(Aes::new(key)?, &nonce.to_le_bytes(), CounterLayout::Nonce64Counter64Le)?;
+    Ok(ctr.encrypt(data))
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set1::challenge1::base64_to_bytes;
+    use crate::set2::challenge10::{ctr_encrypt, ToyFeistel};
+    use super::*;
+
+    #[test]
+    fn test_counter_layouts() {
+        let aes = Aes::new("YELLOW SUBMARINE".as_bytes()).unwrap();
+
+        let ctr = Ctr::new(aes.clone(), &[1, 2, 3, 4, 5, 6, 7, 8], CounterLayout::Nonce64Counter64Le).unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 0x02, 0x01, 0, 0, 0, 0, 0, 0], ctr.counter_block(0x0102));
+
+        let nonce = [0xcau8; 12];
+        let ctr = Ctr::new(aes.clone(), &nonce, CounterLayout::Nonce96Counter32Be).unwrap().with_initial_counter(2);
+        assert_eq!([&nonce[..], &[0, 0, 0x01, 0x04]].concat(), ctr.counter_block(0x0102));
+        assert_eq!([&nonce[..], &[0, 0, 0, 0x01]].concat(), ctr.counter_block(u32::MAX as u64));
+
+        // without overflow of the 32-bit counter this is the plain big endian increment of the whole block
+        let plaintext = vec![0x5au8; 100];
+        assert_eq!(ctr_encrypt(&aes, &ctr.counter_block(0), &plaintext).unwrap(), ctr.encrypt(&plaintext));
+
+        assert_eq!(Some(Error::InvalidNonceLength { length: 12, expected: 8 }), Ctr::new(aes, &nonce, CounterLayout::Nonce64Counter64Le).err());
+        let toy = ToyFeistel::new(8, "YELLOW SUBMARINE".as_bytes()).unwrap();
+        assert_eq!(Some(Error::UnsupportedBlockSize { block_size: 8 }), Ctr::new(toy, &[0u8; 8], CounterLayout::Nonce64Counter64Le).err());
+    }
+
+    #[test]
+    fn test_seekable_keystream() {
+        let aes = Aes::new("YELLOW SUBMARINE".as_bytes()).unwrap();
+        let ctr = Ctr::new(aes, &[0u8; 8], CounterLayout::Nonce64Counter64Le).unwrap();
+
+        let keystream = ctr.keystream(0, 1 << 16);
+        for (offset, len) in [(0, 1), (15, 2), (37, 100), (4096, 4096), (65535, 1)] {
+            assert_eq!(&keystream[offset..offset + len], &ctr.keystream(offset as u64, len)[..]);
+        }
+
+        // decrypt a large buffer in place, then a slice of it independently
+        let plaintext: Vec<u8> = (0..1 << 16).map(|i| i as u8).collect();
+        let mut buffer = plaintext.clone();
+        ctr.apply_keystream(0, &mut buffer);
+        let mut slice = buffer[1000..3000].to_vec();
+        ctr.apply_keystream(1000, &mut slice);
+
+        assert_eq!(&plaintext[1000..3000], &slice[..]);
+        assert_eq!(&plaintext[..1000], &ctr.decrypt(&buffer[..1000])[..]);
+    }
 
     #[test]
     fn run_challenge18() {
+        let ciphertext = base64_to_bytes("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==").unwrap();
+        let key = "YELLOW SUBMARINE".as_bytes();
+
+        let plaintext = String::from_utf8(aes_128_ctr(key, 0, &ciphertext).unwrap()).unwrap();
+        println!("{}", plaintext);
 
+        assert_eq!("Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ", plaintext);
+        assert_eq!(ciphertext, aes_128_ctr(key, 0, plaintext.as_bytes()).unwrap());
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,244 @@
+use crate::error::Error;
+use crate::set1::challenge6::find_repeating_key;
 
 
 
+pub const CHALLENGE19_STRINGS: [&str; 40] = [
+    "SSBoYXZlIG1ldCB0aGVtIGF0IGNsb3NlIG9mIGRheQ==",
+    "Q29taW5nIHdpdGggdml2aWQgZmFjZXM=",
+    "RnJvbSBjb3VudGVyIG9yIGRlc2sgYW1vbmcgZ3JleQ==",
+    "RWlnaHRlZW50aC1jZW50dXJ5IGhvdXNlcy4=",
+    "SSBoYXZlIHBhc3NlZCB3aXRoIGEgbm9kIG9mIHRoZSBoZWFk",
+    "T3IgcG9saXRlIG1lYW5pbmdsZXNzIHdvcmRzLA==",
+    "T3IgaGF2ZSBsaW5nZXJlZCBhd2hpbGUgYW5kIHNhaWQ=",
+    "UG9saXRlIG1lYW5pbmdsZXNzIHdvcmRzLA==",
+    "QW5kIHRob3VnaHQgYmVmb3JlIEkgaGFkIGRvbmU=",
+    "T2YgYSBtb2NraW5nIHRhbGUgb3IgYSBnaWJl",
+    "VG8gcGxlYXNlIGEgY29tcGFuaW9u",
+    "QXJvdW5kIHRoZSBmaXJlIGF0IHRoZSBjbHViLA==",
+    "QmVpbmcgY2VydGFpbiB0aGF0IHRoZXkgYW5kIEk=",
+    "QnV0IGxpdmVkIHdoZXJlIG1vdGxleSBpcyB3b3JuOg==",
+    "QWxsIGNoYW5nZWQsIGNoYW5nZWQgdXR0ZXJseTo=",
+    "QSB0ZXJyaWJsZSBiZWF1dHkgaXMgYm9ybi4=",
+    "VGhhdCB3b21hbidzIGRheXMgd2VyZSBzcGVudA==",
+    "SW4gaWdub3JhbnQgZ29vZCB3aWxsLA==",
+    "SGVyIG5pZ2h0cyBpbiBhcmd1bWVudA==",
+    "VW50aWwgaGVyIHZvaWNlIGdyZXcgc2hyaWxsLg==",
+    "V2hhdCB2b2ljZSBtb3JlIHN3ZWV0IHRoYW4gaGVycw==",
+    "V2hlbiB5b3VuZyBhbmQgYmVhdXRpZnVsLA==",
+    "U2hlIHJvZGUgdG8gaGFycmllcnM/",
+    "VGhpcyBtYW4gaGFkIGtlcHQgYSBzY2hvb2w=",
+    "QW5kIHJvZGUgb3VyIHdpbmdlZCBob3JzZS4=",
+    "VGhpcyBvdGhlciBoaXMgaGVscGVyIGFuZCBmcmllbmQ=",
+    "V2FzIGNvbWluZyBpbnRvIGhpcyBmb3JjZTs=",
+    "SGUgbWlnaHQgaGF2ZSB3b24gZmFtZSBpbiB0aGUgZW5kLA==",
+    "U28gc2Vuc2l0aXZlIGhpcyBuYXR1cmUgc2VlbWVkLA==",
+    "U28gZGFyaW5nIGFuZCBzd2VldCBoaXMgdGhvdWdodC4=",
+    "VGhpcyBvdGhlciBtYW4gSSBoYWQgZHJlYW1lZA==",
+    "QSBkcnVua2VuLCB2YWluLWdsb3Jpb3VzIGxvdXQu",
+    "SGUgaGFkIGRvbmUgbW9zdCBiaXR0ZXIgd3Jvbmc=",
+    "VG8gc29tZSB3aG8gYXJlIG5lYXIgbXkgaGVhcnQs",
+    "WWV0IEkgbnVtYmVyIGhpbSBpbiB0aGUgc29uZzs=",
+    "SGUsIHRvbywgaGFzIHJlc2lnbmVkIGhpcyBwYXJ0",
+    "SW4gdGhlIGNhc3VhbCBjb21lZHk7",
+    "SGUsIHRvbywgaGFzIGJlZW4gY2hhbmdlZCBpbiBoaXMgdHVybiw=",
+    "VHJhbnNmb3JtZWQgdXR0ZXJseTo=",
+    "QSB0ZXJyaWJsZSBiZWF1dHkgaXMgYm9ybi4=",
+];
+
+// Plausibility of a single byte being part of English text, used to rank keystream candidates per column
+// where there are too few bytes for a letter frequency comparison.
+pub fn english_byte_score(b: u8) -> f64 {
+    match b {
+        b' ' => 2.0,
+        b'e' | b't' | b'a' | b'o' | b'i' | b'n' => 1.5,
+        b's' | b'h' | b'r' | b'd' | b'l' | b'u' => 1.2,
+        b'a'..=b'z' => 1.0,
+        b'A'..=b'Z' => 0.8,
+        b',' | b'.' | b'\'' | b'!' | b'?' | b'-' | b';' | b':' => 0.3,
+        b'0'..=b'9' => 0.2,
+        0x21..=0x7e => -0.5,
+        _ => -5.0,
+    }
+}
+
+// Breaks CTR ciphertexts that were all encrypted with the same nonce and key, i.e. xored with the same keystream.
+// The keystream is first recovered statistically over the length shared by all ciphertexts,
+// then extended one column at a time, with manual overrides for known plaintext.
+pub struct FixedNonceCtrBreaker {
+    ciphertexts: Vec<Vec<u8>>,
+    keystream: Vec<u8>,
+}
+
+impl FixedNonceCtrBreaker {
+    pub fn new(ciphertexts: &[Vec<u8>]) -> FixedNonceCtrBreaker {
+        FixedNonceCtrBreaker { ciphertexts: ciphertexts.to_vec(), keystream: Vec::new() }
+    }
+
+    pub fn common_len(&self) -> usize {
+        self.ciphertexts.iter().map(|c| c.len()).min().unwrap_or(0)
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+
+    // Truncate every ciphertext to the common length, concatenated they form a repeating key xor
+    // with a key as long as the common length, which is solved as in challenge6.
+    pub fn recover_common_keystream(&mut self) {
+        let common_len = self.common_len();
+        if common_len == 0 {
+            return;
+        }
+
+        let truncated: Vec<u8> = self.ciphertexts.iter()
+            .flat_map(|c| c[..common_len].iter().copied())
+            .collect();
+        self.keystream = find_repeating_key(common_len, &truncated);
+    }
+
+    // Bytes of every ciphertext long enough to reach the column.
+    pub fn column(&self, column: usize) -> Vec<u8> {
+        self.ciphertexts.iter().filter_map(|c| c.get(column).copied()).collect()
+    }
+
+    // All 256 keystream byte candidates for a column, best first.
+    pub fn column_candidates(&self, column: usize) -> Vec<(u8, f64)> {
+        let bytes = self.column(column);
+        let mut candidates: Vec<(u8, f64)> = (0..=255u8)
+            .map(|k| (k, bytes.iter().map(|b| english_byte_score(b ^ k)).sum()))
+            .collect();
+        candidates.sort_by(|x, y| y.1.partial_cmp(&x.1).unwrap());
+        candidates
+    }
+
+    // Extend the keystream by one column with the best ranked candidate, returns false once past the longest ciphertext.
+    pub fn extend_keystream(&mut self) -> bool {
+        let column = self.keystream.len();
+        if column >= self.max_len() {
+            return false;
+        }
+
+        let best = self.column_candidates(column)[0].0;
+        self.keystream.push(best);
+        true
+    }
+
+    pub fn extend_keystream_to_end(&mut self) {
+        while self.extend_keystream() {}
+    }
+
+    // Replace the keystream byte of a column with the best ranked candidate, useful when the statistics were wrong.
+    pub fn rerank_column(&mut self, column: usize) {
+        if column < self.keystream.len() {
+            self.keystream[column] = self.column_candidates(column)[0].0;
+        }
+    }
+
+    pub fn set_keystream_byte(&mut self, column: usize, value: u8) {
+        if column >= self.keystream.len() {
+            self.keystream.resize(column + 1, 0);
+        }
+        self.keystream[column] = value;
+    }
+
+    // Known plaintext fragment at some offset of one of the ciphertexts fixes the keystream for those columns.
+    // The fragment must lie entirely inside that ciphertext.
+    pub fn apply_known_plaintext(&mut self, index: usize, offset: usize, fragment: &[u8]) -> Result<(), Error> {
+        let Some(ciphertext) = self.ciphertexts.get(index).cloned() else {
+            return Err(Error::OutOfBounds { offset: index, length: 1, size: self.ciphertexts.len() });
+        };
+        let size = ciphertext.len();
+        if offset.checked_add(fragment.len()).is_none_or(|end| end > size) {
+            return Err(Error::OutOfBounds { offset, length: fragment.len(), size });
+        }
+
+        for (i, (p, c)) in fragment.iter().zip(&ciphertext[offset..]).enumerate() {
+            self.set_keystream_byte(offset + i, p ^ c);
+        }
+        Ok(())
+    }
+
+    pub fn keystream(&self) -> &[u8] {
+        &self.keystream
+    }
+
+    // Each ciphertext decrypted as far as the keystream reaches.
+    pub fn plaintexts(&self) -> Vec<Vec<u8>> {
+        self.ciphertexts.iter()
+            .map(|c| c.iter().zip(&self.keystream).map(|(c, k)| c ^ k).collect())
+            .collect()
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set1::challenge1::base64_to_bytes;
+    use crate::set2::challenge11::random_aes_key;
+    use crate::set3::challenge18::aes_128_ctr;
+    use super::*;
+
+    #[test]
+    fn test_column_candidates() {
+        let keystream = aes_128_ctr(&random_aes_key(), 0, &[0u8; 2]).unwrap();
+        let ciphertexts: Vec<Vec<u8>> = ["he", "to", "an", "it"].iter()
+            .map(|p| p.bytes().zip(&keystream).map(|(p, k)| p ^ k).collect())
+            .collect();
+
+        let mut breaker = FixedNonceCtrBreaker::new(&ciphertexts);
+        assert_eq!(keystream[0], breaker.column_candidates(0)[0].0);
+
+        breaker.extend_keystream_to_end();
+        assert_eq!(&keystream, breaker.keystream());
+        assert!(!breaker.extend_keystream());
+
+        // a wrong byte is put back by ranking the column again
+        breaker.set_keystream_byte(1, !keystream[1]);
+        breaker.rerank_column(1);
+        assert_eq!(&keystream, breaker.keystream());
+    }
+
+    #[test]
+    fn test_apply_known_plaintext() {
+        let key = random_aes_key();
+        let plaintexts: Vec<&[u8]> = vec![b"known plaintext", b"fixes the keystream bytes"];
+        let ciphertexts: Vec<Vec<u8>> = plaintexts.iter().map(|p| aes_128_ctr(&key, 0, p).unwrap()).collect();
+        let keystream = aes_128_ctr(&key, 0, &[0u8; 25]).unwrap();
+
+        let mut breaker = FixedNonceCtrBreaker::new(&ciphertexts);
+        assert_eq!(Ok(()), breaker.apply_known_plaintext(1, 10, b"keystream"));
+        assert_eq!(&keystream[10..19], &breaker.keystream()[10..]);
+        assert_eq!(Ok(()), breaker.apply_known_plaintext(0, 0, b"known"));
+        assert_eq!(&keystream[..5], &breaker.keystream()[..5]);
+
+        assert_eq!(Err(Error::OutOfBounds { offset: 2, length: 1, size: 2 }), breaker.apply_known_plaintext(2, 0, b"k"));
+        assert_eq!(Err(Error::OutOfBounds { offset: 16, length: 1, size: 15 }), breaker.apply_known_plaintext(0, 16, b"x"));
+        assert_eq!(Err(Error::OutOfBounds { offset: 10, length: 9, size: 15 }), breaker.apply_known_plaintext(0, 10, b"plaintext"));
+        assert_eq!(Err(Error::OutOfBounds { offset: usize::MAX, length: 1, size: 15 }), breaker.apply_known_plaintext(0, usize::MAX, b"x"));
+    }
 
     #[test]
     fn run_challenge19() {
+        let key = random_aes_key();
+        let plaintexts: Vec<Vec<u8>> = CHALLENGE19_STRINGS.iter().map(|s| base64_to_bytes(s).unwrap()).collect();
+        let ciphertexts: Vec<Vec<u8>> = plaintexts.iter().map(|p| aes_128_ctr(&key, 0, p).unwrap()).collect();
+
+        let mut breaker = FixedNonceCtrBreaker::new(&ciphertexts);
+        breaker.recover_common_keystream();
+
+        // the statistics alone get most of the common columns right
+        let keystream = aes_128_ctr(&key, 0, &vec![0u8; breaker.max_len()]).unwrap();
+        let common_len = breaker.common_len();
+        let correct = (0..common_len).filter(|&i| breaker.keystream()[i] == keystream[i]).count();
+        println!("common_len = {}, correct = {}", common_len, correct);
+        assert!(correct >= 16);
 
+        breaker.extend_keystream_to_end();
+        for p in breaker.plaintexts() {
+            println!("{}", String::from_utf8_lossy(&p));
+        }
     }
 
-}
\ No newline at end of file
+}
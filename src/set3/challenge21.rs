@@ -1,14 +1,235 @@
+use rand_core::{impls, Error as RandError, RngCore, SeedableRng};
 
 
+// MT19937 parameters, see https://en.wikipedia.org/wiki/Mersenne_Twister
+pub const MT_N: usize = 624;
+const MT_M: usize = 397;
+const MT_MATRIX_A: u32 = 0x9908b0df;
+const MT_UPPER_MASK: u32 = 0x80000000;
+const MT_LOWER_MASK: u32 = 0x7fffffff;
+const MT_F: u32 = 1812433253;
+
+// MT19937-64 parameters
+pub const MT64_N: usize = 312;
+const MT64_M: usize = 156;
+const MT64_MATRIX_A: u64 = 0xb5026f5aa96619e9;
+const MT64_UPPER_MASK: u64 = 0xffffffff80000000;
+const MT64_LOWER_MASK: u64 = 0x7fffffff;
+const MT64_F: u64 = 6364136223846793005;
+
+pub fn temper(state_word: u32) -> u32 {
+    let mut y = state_word;
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c5680;
+    y ^= (y << 15) & 0xefc60000;
+    y ^= y >> 18;
+    y
+}
+
+pub fn temper64(state_word: u64) -> u64 {
+    let mut y = state_word;
+    y ^= (y >> 29) & 0x5555555555555555;
+    y ^= (y << 17) & 0x71d67fffeda60000;
+    y ^= (y << 37) & 0xfff7eee000000000;
+    y ^= y >> 43;
+    y
+}
+
+// 32-bit Mersenne Twister.
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; MT_N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Mt19937 {
+        let mut state = [0u32; MT_N];
+        state[0] = seed;
+        for i in 1..MT_N {
+            state[i] = MT_F.wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30)).wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: MT_N }
+    }
+
+    // Resume from a raw (untempered) state, index is the position of the next word to temper.
+    pub fn from_state(state: [u32; MT_N], index: usize) -> Mt19937 {
+        Mt19937 { state, index: index.min(MT_N) }
+    }
+
+    pub fn state(&self) -> &[u32; MT_N] {
+        &self.state
+    }
+
+    fn twist(&mut self) {
+        for i in 0..MT_N {
+            let y = (self.state[i] & MT_UPPER_MASK) | (self.state[(i + 1) % MT_N] & MT_LOWER_MASK);
+            let mut next = self.state[(i + MT_M) % MT_N] ^ (y >> 1);
+            if y & 1 != 0 {
+                next ^= MT_MATRIX_A;
+            }
+            self.state[i] = next;
+        }
+        self.index = 0;
+    }
+
+    pub fn extract_number(&mut self) -> u32 {
+        if self.index >= MT_N {
+            self.twist();
+        }
+        let y = temper(self.state[self.index]);
+        self.index += 1;
+        y
+    }
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        self.extract_number()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937 {
+    type Seed = [u8; 4];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Mt19937::new(u32::from_le_bytes(seed))
+    }
+}
+
+// 64-bit Mersenne Twister.
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; MT64_N],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    pub fn new(seed: u64) -> Mt19937_64 {
+        let mut state = [0u64; MT64_N];
+        state[0] = seed;
+        for i in 1..MT64_N {
+            state[i] = MT64_F.wrapping_mul(state[i - 1] ^ (state[i - 1] >> 62)).wrapping_add(i as u64);
+        }
+        Mt19937_64 { state, index: MT64_N }
+    }
+
+    pub fn from_state(state: [u64; MT64_N], index: usize) -> Mt19937_64 {
+        Mt19937_64 { state, index: index.min(MT64_N) }
+    }
+
+    pub fn state(&self) -> &[u64; MT64_N] {
+        &self.state
+    }
+
+    fn twist(&mut self) {
+        for i in 0..MT64_N {
+            let y = (self.state[i] & MT64_UPPER_MASK) | (self.state[(i + 1) % MT64_N] & MT64_LOWER_MASK);
+            let mut next = self.state[(i + MT64_M) % MT64_N] ^ (y >> 1);
+            if y & 1 != 0 {
+                next ^= MT64_MATRIX_A;
+            }
+            self.state[i] = next;
+        }
+        self.index = 0;
+    }
+
+    pub fn extract_number(&mut self) -> u64 {
+        if self.index >= MT64_N {
+            self.twist();
+        }
+        let y = temper64(self.state[self.index]);
+        self.index += 1;
+        y
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    // the low half of each 64-bit output
+    fn next_u32(&mut self) -> u32 {
+        self.extract_number() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.extract_number()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937_64 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Mt19937_64::new(u64::from_le_bytes(seed))
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use rand::Rng;
+
+    use super::*;
 
     #[test]
     fn run_challenge21() {
+        // reference outputs for the default seed of the original implementation
+        let mut mt = Mt19937::new(5489);
+        let outputs: Vec<u32> = (0..5).map(|_| mt.extract_number()).collect();
+        assert_eq!(vec![3499211612, 581869302, 3890346734, 3586334585, 545404204], outputs);
+
+        // the C++ standard requires the 10000th output of a default constructed std::mt19937 to be 4123659995
+        let mut mt = Mt19937::new(5489);
+        let output = (0..10000).map(|_| mt.extract_number()).last().unwrap();
+        assert_eq!(4123659995, output);
+    }
+
+    #[test]
+    fn test_mt19937_64() {
+        let mut mt = Mt19937_64::new(5489);
+        assert_eq!(14514284786278117030, mt.extract_number());
+
+        // the C++ standard requires the 10000th output of a default constructed std::mt19937_64 to be 9981545732273789042
+        let mut mt = Mt19937_64::new(5489);
+        let output = (0..10000).map(|_| mt.extract_number()).last().unwrap();
+        assert_eq!(9981545732273789042, output);
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut mt = Mt19937::from_seed(5489u32.to_le_bytes());
+        assert_eq!(3499211612, mt.next_u32());
+
+        let mut bytes = [0u8; 6];
+        mt.fill_bytes(&mut bytes);
+        assert_eq!(&581869302u32.to_le_bytes(), &bytes[..4]);
 
+        // usable wherever the rand traits are expected
+        let mut mt = Mt19937_64::seed_from_u64(42);
+        let x: u8 = mt.gen_range(5..=10);
+        assert!((5..=10).contains(&x));
     }
 
-}
\ No newline at end of file
+}
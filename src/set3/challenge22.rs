@@ -1,14 +1,113 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use rand::Rng;
+use rand_core::RngCore;
+
+use crate::set3::challenge21::Mt19937;
+
+
+// Source of unix timestamps in seconds, so the attacks can be tested without actually waiting.
+pub trait Clock {
+    fn now(&self) -> u64;
+    fn sleep(&self, seconds: u64);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn sleep(&self, seconds: u64) {
+        std::thread::sleep(std::time::Duration::from_secs(seconds));
+    }
+}
+
+// Clock that only moves when asked to sleep.
+pub struct VirtualClock {
+    now: Cell<u64>,
+}
+
+impl VirtualClock {
+    pub fn new(now: u64) -> VirtualClock {
+        VirtualClock { now: Cell::new(now) }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    fn sleep(&self, seconds: u64) {
+        self.now.set(self.now.get() + seconds);
+    }
+}
+
+// Wait a random while, seed MT19937 with the current timestamp, wait again and return the first output.
+pub fn time_seeded_output(clock: &impl Clock) -> u32 {
+    let mut rng = rand::thread_rng();
+
+    clock.sleep(rng.gen_range(40..=1000));
+    let mut mt = Mt19937::new(clock.now() as u32);
+    clock.sleep(rng.gen_range(40..=1000));
+
+    mt.next_u32()
+}
+
+// Try every timestamp in the window as a seed and return the one whose first outputs match.
+pub fn crack_time_seed(outputs: &[u32], window_start: u64, window_end: u64) -> Option<u32> {
+    if outputs.is_empty() {
+        return None;
+    }
+
+    (window_start..=window_end)
+        .map(|timestamp| timestamp as u32)
+        .find(|&seed| {
+            let mut mt = Mt19937::new(seed);
+            outputs.iter().all(|&output| mt.next_u32() == output)
+        })
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = VirtualClock::new(1_000);
+        clock.sleep(40);
+        assert_eq!(1_040, clock.now());
+        assert!(SystemClock.now() > 1_600_000_000);
+    }
+
+    #[test]
+    fn test_crack_time_seed_several_outputs() {
+        let seed = 1_700_000_123u32;
+        let mut mt = Mt19937::new(seed);
+        let outputs: Vec<u32> = (0..3).map(|_| mt.next_u32()).collect();
+
+        assert_eq!(Some(seed), crack_time_seed(&outputs, seed as u64 - 100, seed as u64 + 100));
+        assert_eq!(None, crack_time_seed(&outputs, seed as u64 + 1, seed as u64 + 100));
+    }
 
     #[test]
     fn run_challenge22() {
+        let clock = VirtualClock::new(1_700_000_000);
+        let start = clock.now();
+
+        let output = time_seeded_output(&clock);
+
+        // the seed is somewhere between the start of the routine and the time the output was received
+        let seed = crack_time_seed(&[output], start, clock.now()).unwrap();
+        println!("recovered seed = {}", seed);
 
+        assert!(seed as u64 >= start + 40 && seed as u64 <= clock.now() - 40);
+        assert_eq!(output, Mt19937::new(seed).next_u32());
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,114 @@
+use crate::error::Error;
+use crate::set3::challenge21::{Mt19937, Mt19937_64, MT64_N, MT_N};
 
 
+// Invert y = x ^ ((x >> shift) & mask), each pass recovers another `shift` high bits of x.
+fn undo_right_shift_xor(y: u64, shift: u32, mask: u64, bits: u32) -> u64 {
+    let mut x = y;
+    for _ in 0..bits.div_ceil(shift) {
+        x = y ^ ((x >> shift) & mask);
+    }
+    x
+}
+
+// Invert y = x ^ ((x << shift) & mask), each pass recovers another `shift` low bits of x.
+fn undo_left_shift_xor(y: u64, shift: u32, mask: u64, bits: u32) -> u64 {
+    let width_mask = if bits == 64 { u64::MAX } else { (1u64 << bits) - 1 };
+    let mut x = y;
+    for _ in 0..bits.div_ceil(shift) {
+        x = (y ^ ((x << shift) & mask)) & width_mask;
+    }
+    x
+}
+
+pub fn untemper(output: u32) -> u32 {
+    let mut y = output as u64;
+    y = undo_right_shift_xor(y, 18, 0xffffffff, 32);
+    y = undo_left_shift_xor(y, 15, 0xefc60000, 32);
+    y = undo_left_shift_xor(y, 7, 0x9d2c5680, 32);
+    y = undo_right_shift_xor(y, 11, 0xffffffff, 32);
+    y as u32
+}
+
+pub fn untemper64(output: u64) -> u64 {
+    let mut y = output;
+    y = undo_right_shift_xor(y, 43, u64::MAX, 64);
+    y = undo_left_shift_xor(y, 37, 0xfff7eee000000000, 64);
+    y = undo_left_shift_xor(y, 17, 0x71d67fffeda60000, 64);
+    y = undo_right_shift_xor(y, 29, 0x5555555555555555, 64);
+    y
+}
+
+// Rebuild the generator from 624 consecutive outputs, taken right after a twist (e.g. from a fresh generator).
+// The clone then predicts every following output.
+pub fn clone_from_outputs(outputs: &[u32]) -> Result<Mt19937, Error> {
+    if outputs.len() < MT_N {
+        return Err(Error::AttackFailed(format!("need {} outputs, got {}", MT_N, outputs.len())));
+    }
+
+    let mut state = [0u32; MT_N];
+    for (s, &output) in state.iter_mut().zip(&outputs[outputs.len() - MT_N..]) {
+        *s = untemper(output);
+    }
+    Ok(Mt19937::from_state(state, MT_N))
+}
+
+// Same as clone_from_outputs with the 312 outputs of a twist of MT19937-64.
+pub fn clone_from_outputs_64(outputs: &[u64]) -> Result<Mt19937_64, Error> {
+    if outputs.len() < MT64_N {
+        return Err(Error::AttackFailed(format!("need {} outputs, got {}", MT64_N, outputs.len())));
+    }
+
+    let mut state = [0u64; MT64_N];
+    for (s, &output) in state.iter_mut().zip(&outputs[outputs.len() - MT64_N..]) {
+        *s = untemper64(output);
+    }
+    Ok(Mt19937_64::from_state(state, MT64_N))
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use rand::RngCore;
+
+    use crate::set3::challenge21::{temper, temper64};
+    use super::*;
+
+    #[test]
+    fn test_untemper() {
+        for x in [0u32, 1, 0xdeadbeef, 0x80000000, u32::MAX] {
+            assert_eq!(x, untemper(temper(x)));
+        }
+        for x in [0u64, 1, 0xdeadbeefcafebabe, 0x8000000000000000, u64::MAX] {
+            assert_eq!(x, untemper64(temper64(x)));
+        }
+    }
 
     #[test]
     fn run_challenge23() {
+        let mut mt = Mt19937::new(rand::random());
+        let outputs: Vec<u32> = (0..MT_N).map(|_| mt.next_u32()).collect();
+
+        let mut cloned = clone_from_outputs(&outputs).unwrap();
+        assert_eq!(mt.state(), cloned.state());
+        for _ in 0..2000 {
+            assert_eq!(mt.next_u32(), cloned.next_u32());
+        }
+
+        assert!(clone_from_outputs(&outputs[1..]).is_err());
+    }
+
+    #[test]
+    fn test_clone_mt19937_64() {
+        let mut mt = Mt19937_64::new(rand::random());
+        let outputs: Vec<u64> = (0..MT64_N).map(|_| mt.next_u64()).collect();
 
+        let mut cloned = clone_from_outputs_64(&outputs).unwrap();
+        assert_eq!(mt.state(), cloned.state());
+        for _ in 0..1000 {
+            assert_eq!(mt.next_u64(), cloned.next_u64());
+        }
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,107 @@
+use rand::Rng;
+use rand_core::RngCore;
 
+use crate::set1::challenge2::fixed_xor;
+use crate::set2::challenge11::random_bytes;
+use crate::set3::challenge21::Mt19937;
+use crate::set3::challenge22::Clock;
+
+
+pub const RESET_TOKEN_LEN: usize = 16;
+
+fn mt_keystream(seed: u32, len: usize) -> Vec<u8> {
+    let mut keystream = vec![0u8; len];
+    Mt19937::new(seed).fill_bytes(&mut keystream);
+    keystream
+}
+
+// Stream cipher xoring the data with the bytes of MT19937 seeded by a 16-bit key, each output gives 4 bytes little endian.
+pub fn mt_stream_cipher(key: u16, data: &[u8]) -> Vec<u8> {
+    fixed_xor(data, &mt_keystream(key as u32, data.len()))
+}
+
+// Encrypt a random count of random bytes followed by the known plaintext.
+pub fn encrypt_with_random_prefix(key: u16, known_plaintext: &[u8]) -> Vec<u8> {
+    let prefix = random_bytes(rand::thread_rng().gen_range(0..=32));
+    mt_stream_cipher(key, &[&prefix, known_plaintext].concat())
+}
+
+// The key space is only 16 bits, so try every key against the known plaintext at the end of the ciphertext.
+// An empty suffix matches every key, so there is nothing to recover.
+pub fn recover_mt_stream_key(ciphertext: &[u8], known_suffix: &[u8]) -> Option<u16> {
+    if known_suffix.is_empty() || known_suffix.len() > ciphertext.len() {
+        return None;
+    }
+
+    let offset = ciphertext.len() - known_suffix.len();
+    let expected_keystream = fixed_xor(&ciphertext[offset..], known_suffix);
+
+    (0..=u16::MAX).find(|&key| mt_keystream(key as u32, ciphertext.len())[offset..] == expected_keystream[..])
+}
+
+// Password reset token made of MT19937 output seeded with the current time.
+pub fn generate_reset_token(clock: &impl Clock) -> Vec<u8> {
+    mt_keystream(clock.now() as u32, RESET_TOKEN_LEN)
+}
+
+// A token generated by a time seeded MT19937 in the last window_seconds is found by trying each of those timestamps.
+// An empty token would match any timestamp and is never accepted.
+pub fn is_time_seeded_token(token: &[u8], clock: &impl Clock, window_seconds: u64) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+
+    let now = clock.now();
+    (now.saturating_sub(window_seconds)..=now).any(|timestamp| mt_keystream(timestamp as u32, token.len()) == token)
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set3::challenge22::VirtualClock;
+    use super::*;
+
+    #[test]
+    fn test_mt_stream_cipher() {
+        let plaintext = "Cooking MC's like a pound of bacon".as_bytes();
+        let ciphertext = mt_stream_cipher(0xbeef, plaintext);
+
+        assert_ne!(plaintext, &ciphertext[..]);
+        assert_eq!(plaintext, &mt_stream_cipher(0xbeef, &ciphertext)[..]);
+    }
 
     #[test]
     fn run_challenge24() {
+        let key: u16 = rand::random();
+        let known_plaintext = "AAAAAAAAAAAAAA".as_bytes();
+
+        let ciphertext = encrypt_with_random_prefix(key, known_plaintext);
+        let recovered = recover_mt_stream_key(&ciphertext, known_plaintext);
+        println!("key = {}, recovered = {:?}", key, recovered);
+
+        assert_eq!(Some(key), recovered);
+    }
+
+    #[test]
+    fn test_recover_key_with_empty_suffix() {
+        let ciphertext = mt_stream_cipher(0xbeef, "AAAA".as_bytes());
+        assert_eq!(None, recover_mt_stream_key(&ciphertext, &[]));
+        assert_eq!(None, recover_mt_stream_key(&[], &[]));
+    }
+
+    #[test]
+    fn test_reset_token_detection() {
+        let clock = VirtualClock::new(1_700_000_000);
+
+        let token = generate_reset_token(&clock);
+        clock.sleep(300);
+        assert!(is_time_seeded_token(&token, &clock, 3600));
+
+        let random_token = random_bytes(RESET_TOKEN_LEN);
+        assert!(!is_time_seeded_token(&random_token, &clock, 3600));
 
+        assert!(!is_time_seeded_token(&[], &clock, 3600));
     }
 
-}
\ No newline at end of file
+}
@@ -1,8 +1,8 @@
-mod challenge17;
-mod challenge18;
-mod challenge19;
-mod challenge20;
-mod challenge21;
-mod challenge22;
-mod challenge23;
-mod challenge24;
\ No newline at end of file
+pub mod challenge17;
+pub mod challenge18;
+pub mod challenge19;
+pub mod challenge20;
+pub mod challenge21;
+pub mod challenge22;
+pub mod challenge23;
+pub mod challenge24;
\ No newline at end of file
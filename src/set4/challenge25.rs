@@ -1,14 +1,87 @@
+use crate::error::Error;
+use crate::set1::challenge2::fixed_xor;
+use crate::set1::challenge7::Aes;
+use crate::set2::challenge11::random_aes_key;
+use crate::set3::challenge18::{CounterLayout, Ctr};
 
 
+// Ciphertext stored under AES-CTR with a secret key, that can be rewritten at any offset.
+pub struct CtrDisk {
+    ctr: Ctr<Aes>,
+    ciphertext: Vec<u8>,
+}
+
+impl CtrDisk {
+    pub fn new(plaintext: &[u8]) -> CtrDisk {
+        let ctr = Ctr::new(Aes::new(&random_aes_key()).unwrap(), &rand::random::<u64>().to_le_bytes(), CounterLayout::Nonce64Counter64Le).unwrap();
+        let ciphertext = ctr.encrypt(plaintext);
+        CtrDisk { ctr, ciphertext }
+    }
+
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    // Replace the plaintext at offset with new_text, only the keystream covering the edited range is generated.
+    pub fn edit(&mut self, offset: usize, new_text: &[u8]) -> Result<(), Error> {
+        let size = self.ciphertext.len();
+        if offset.checked_add(new_text.len()).is_none_or(|end| end > size) {
+            return Err(Error::OutOfBounds { offset, length: new_text.len(), size });
+        }
+
+        let mut encrypted = new_text.to_vec();
+        self.ctr.apply_keystream(offset as u64, &mut encrypted);
+        self.ciphertext[offset..offset + new_text.len()].copy_from_slice(&encrypted);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn plaintext(&self) -> Vec<u8> {
+        self.ctr.decrypt(&self.ciphertext)
+    }
+}
+
+// Writing zeros over the whole disk makes it hand back the raw keystream, which xored with the
+// original ciphertext gives the plaintext.
+pub fn recover_plaintext_via_edit(ciphertext: &[u8], mut edit: impl FnMut(usize, &[u8]) -> Result<Vec<u8>, Error>) -> Result<Vec<u8>, Error> {
+    let keystream = edit(0, &vec![0u8; ciphertext.len()])?;
+    Ok(fixed_xor(ciphertext, &keystream))
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set1::challenge7::challenge7_plaintext;
+    use super::*;
+
+    #[test]
+    fn test_ctr_disk_edit() {
+        let mut disk = CtrDisk::new(b"YELLOW SUBMARINE YELLOW SUBMARINE");
+        let original = disk.ciphertext().to_vec();
+
+        disk.edit(17, b"PURPLE").unwrap();
+        assert_eq!(b"YELLOW SUBMARINE PURPLE SUBMARINE".to_vec(), disk.plaintext());
+        // bytes outside of the edited range are left untouched
+        assert_eq!(original[..17], disk.ciphertext()[..17]);
+        assert_eq!(original[23..], disk.ciphertext()[23..]);
+
+        assert_eq!(Err(Error::OutOfBounds { offset: 30, length: 6, size: 33 }), disk.edit(30, b"YELLOW"));
+        assert!(disk.edit(usize::MAX, b"A").is_err());
+    }
 
     #[test]
     fn run_challenge25() {
+        let plaintext = challenge7_plaintext();
+        let mut disk = CtrDisk::new(&plaintext);
+        let ciphertext = disk.ciphertext().to_vec();
+
+        let recovered = recover_plaintext_via_edit(&ciphertext, |offset, new_text| {
+            disk.edit(offset, new_text)?;
+            Ok(disk.ciphertext().to_vec())
+        }).unwrap();
 
+        assert_eq!(plaintext, recovered);
     }
 
-}
\ No newline at end of file
+}
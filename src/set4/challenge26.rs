@@ -1,14 +1,77 @@
+use crate::error::Error;
+use crate::set1::challenge7::Aes;
+use crate::set2::challenge11::random_aes_key;
+use crate::set2::challenge16::CommentCipher;
+use crate::set3::challenge18::{CounterLayout, Ctr};
 
 
+// AES-128 CTR under a key and nonce unknown to the user.
+pub struct CtrCommentCipher {
+    ctr: Ctr<Aes>,
+}
+
+impl CtrCommentCipher {
+    pub fn new() -> CtrCommentCipher {
+        let ctr = Ctr::new(Aes::new(&random_aes_key()).unwrap(), &rand::random::<u64>().to_le_bytes(), CounterLayout::Nonce64Counter64Le).unwrap();
+        CtrCommentCipher { ctr }
+    }
+}
+
+impl Default for CtrCommentCipher {
+    fn default() -> Self {
+        CtrCommentCipher::new()
+    }
+}
+
+impl CommentCipher for CtrCommentCipher {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        self.ctr.encrypt(plaintext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.ctr.decrypt(ciphertext))
+    }
+}
+
+// In CTR a flipped ciphertext bit flips the same plaintext bit, and nothing else is damaged.
+pub fn ctr_flip(ciphertext: &mut [u8], offset: usize, known_plaintext: &[u8], target: &[u8]) -> Result<(), Error> {
+    if known_plaintext.len() != target.len() {
+        return Err(Error::LengthMismatch { expected: known_plaintext.len(), actual: target.len() });
+    }
+    if offset.checked_add(target.len()).is_none_or(|end| end > ciphertext.len()) {
+        return Err(Error::OutOfBounds { offset, length: target.len(), size: ciphertext.len() });
+    }
+
+    for ((c, k), t) in ciphertext[offset..].iter_mut().zip(known_plaintext).zip(target) {
+        *c ^= k ^ t;
+    }
+    Ok(())
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set2::challenge16::{forge_admin_comment, CommentService, ADMIN_MARKER};
+    use super::*;
+
+    #[test]
+    fn test_ctr_flip() {
+        let mut ciphertext = vec![0u8; 8];
+        assert_eq!(Ok(()), ctr_flip(&mut ciphertext, 2, b"AB", b"BA"));
+        assert_eq!(vec![0, 0, 3, 3, 0, 0, 0, 0], ciphertext);
+        assert_eq!(Err(Error::OutOfBounds { offset: 7, length: 2, size: 8 }), ctr_flip(&mut ciphertext, 7, b"AB", b"BA"));
+        assert_eq!(Err(Error::OutOfBounds { offset: usize::MAX, length: 2, size: 8 }), ctr_flip(&mut ciphertext, usize::MAX, b"AB", b"BA"));
+        assert_eq!(Err(Error::LengthMismatch { expected: 2, actual: 1 }), ctr_flip(&mut ciphertext, 2, b"AB", b"B"));
+    }
 
     #[test]
     fn run_challenge26() {
+        let service = CommentService::with_cipher(CtrCommentCipher::new());
+        assert_eq!(Ok(false), service.is_admin(&service.encrypt(ADMIN_MARKER)));
 
+        let forged = forge_admin_comment(|user_data| service.encrypt(user_data), ctr_flip).unwrap();
+        assert_eq!(Ok(true), service.is_admin(&forged));
     }
 
-}
\ No newline at end of file
+}
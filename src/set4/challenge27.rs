@@ -1,14 +1,96 @@
+use crate::error::Error;
+use crate::set1::challenge2::fixed_xor;
+use crate::set1::challenge7::AES_BLOCK_SIZE;
+use crate::set2::challenge10::{aes_128_cbc_decrypt, aes_128_cbc_encrypt};
+use crate::set2::challenge11::random_aes_key;
+use crate::set2::challenge16::CommentCipher;
 
 
+// AES-128 CBC that reuses the key as the IV, and rejects non-ASCII plaintext by returning it in the error.
+pub struct KeyAsIvCipher {
+    key: Vec<u8>,
+}
+
+impl KeyAsIvCipher {
+    pub fn new() -> KeyAsIvCipher {
+        KeyAsIvCipher { key: random_aes_key() }
+    }
+
+    #[cfg(test)]
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Default for KeyAsIvCipher {
+    fn default() -> Self {
+        KeyAsIvCipher::new()
+    }
+}
+
+impl CommentCipher for KeyAsIvCipher {
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        aes_128_cbc_encrypt(&self.key, &self.key, plaintext).unwrap()
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = aes_128_cbc_decrypt(&self.key, &self.key, ciphertext)?;
+        if !plaintext.is_ascii() {
+            return Err(Error::NonAsciiPlaintext { plaintext });
+        }
+        Ok(plaintext)
+    }
+}
+
+// Send C1 || 0 || C1 followed by the rest of the ciphertext, which keeps the padding valid.
+// The first block decrypts to D(C1) ^ key and the third to D(C1), so xoring the leaked blocks gives the key.
+// The oracle is any receiver of the ciphertext, only the plaintext leaked in its error is used.
+pub fn recover_key_as_iv(ciphertext: &[u8], oracle: impl Fn(&[u8]) -> Result<(), Error>) -> Result<Vec<u8>, Error> {
+    if ciphertext.len() < 2 * AES_BLOCK_SIZE {
+        return Err(Error::AttackFailed("ciphertext must be at least two blocks long".to_owned()));
+    }
+
+    let first_block = &ciphertext[..AES_BLOCK_SIZE];
+    let modified = [first_block, &[0u8; AES_BLOCK_SIZE], first_block, &ciphertext[AES_BLOCK_SIZE..]].concat();
+
+    match oracle(&modified) {
+        Err(Error::NonAsciiPlaintext { plaintext }) =>
+            Ok(fixed_xor(&plaintext[..AES_BLOCK_SIZE], &plaintext[2 * AES_BLOCK_SIZE..3 * AES_BLOCK_SIZE])),
+        Err(e) => Err(e),
+        Ok(()) => Err(Error::AttackFailed("modified ciphertext decrypted to ascii".to_owned())),
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set2::challenge10::aes_128_cbc_decrypt;
+    use crate::set2::challenge16::{CommentService, COMMENT_PREFIX};
+    use super::*;
+
+    #[test]
+    fn test_non_ascii_plaintext_is_returned() {
+        let cipher = KeyAsIvCipher::new();
+        let ciphertext = cipher.encrypt(b"caf\xc3\xa9");
+
+        assert_eq!(Err(Error::NonAsciiPlaintext { plaintext: b"caf\xc3\xa9".to_vec() }), cipher.decrypt(&ciphertext));
+        assert_eq!(Ok(b"cafe".to_vec()), cipher.decrypt(&cipher.encrypt(b"cafe")));
+    }
 
     #[test]
     fn run_challenge27() {
+        let cipher = KeyAsIvCipher::new();
+        let key = cipher.key().to_vec();
+        let service = CommentService::with_cipher(cipher);
+
+        let ciphertext = service.encrypt("anything");
+        let recovered = recover_key_as_iv(&ciphertext, |ct| service.is_admin(ct).map(|_| ())).unwrap();
+        assert_eq!(key, recovered);
 
+        // with the key the attacker can decrypt any message
+        let plaintext = aes_128_cbc_decrypt(&recovered, &recovered, &ciphertext).unwrap();
+        assert!(plaintext.starts_with(COMMENT_PREFIX.as_bytes()));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,216 @@
+// Incremental hash function, implemented by the in-crate hashes so MACs and attacks can be written once.
+pub trait HashFunction: Clone {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
 
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// Merkle-Damgard padding for a message of the given length in bytes: a 1 bit, zeros and the message length in bits
+// in the last eighth of the final block (8 bytes for 64 byte blocks, 16 bytes for 128 byte blocks).
+pub fn md_padding(message_len: u64, block_size: usize, big_endian: bool) -> Vec<u8> {
+    let length_size = block_size / 8;
+    let zeros = (2 * block_size - 1 - length_size - (message_len as usize % block_size)) % block_size;
+    let bit_len = (message_len as u128) * 8;
+
+    let mut padding = vec![0x80];
+    padding.extend(vec![0u8; zeros]);
+    if big_endian {
+        padding.extend(&bit_len.to_be_bytes()[16 - length_size..]);
+    } else {
+        padding.extend(&bit_len.to_le_bytes()[..length_size]);
+    }
+    padding
+}
+
+// Append data to the partial block buffer and compress every block that is now complete.
+pub fn buffer_blocks(buffer: &mut Vec<u8>, data: &[u8], block_size: usize, mut compress: impl FnMut(&[u8])) {
+    buffer.extend_from_slice(data);
+
+    let whole = buffer.len() - buffer.len() % block_size;
+    for block in buffer[..whole].chunks(block_size) {
+        compress(block);
+    }
+    buffer.drain(..whole);
+}
+
+pub const SHA1_BLOCK_SIZE: usize = 64;
+pub const SHA1_OUTPUT_SIZE: usize = 20;
+
+const SHA1_INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+// SHA-1 stores the message length big endian.
+pub fn sha1_padding(message_len: u64) -> Vec<u8> {
+    md_padding(message_len, SHA1_BLOCK_SIZE, true)
+}
+
+fn sha1_compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, wi) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*wi);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+// Incremental SHA-1, the state can be set to any value to continue hashing from a known digest.
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: Vec<u8>,
+    processed_len: u64,
+}
+
+impl Sha1 {
+    pub fn new() -> Sha1 {
+        Sha1::from_state(SHA1_INITIAL_STATE, 0)
+    }
+
+    // Resume from an internal state after processed_len bytes, which must be a whole number of blocks.
+    pub fn from_state(state: [u32; 5], processed_len: u64) -> Sha1 {
+        Sha1 { state, buffer: Vec::with_capacity(SHA1_BLOCK_SIZE), processed_len }
+    }
+
+    // The internal state encoded in a digest.
+    pub fn state_from_digest(digest: &[u8; SHA1_OUTPUT_SIZE]) -> [u32; 5] {
+        let mut state = [0u32; 5];
+        for (s, word) in state.iter_mut().zip(digest.chunks(4)) {
+            *s = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        state
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.processed_len += data.len() as u64;
+        let state = &mut self.state;
+        buffer_blocks(&mut self.buffer, data, SHA1_BLOCK_SIZE, |block| sha1_compress(state, block));
+    }
+
+    pub fn finalize(mut self) -> [u8; SHA1_OUTPUT_SIZE] {
+        let padding = sha1_padding(self.processed_len);
+        self.update(&padding);
+
+        let mut digest = [0u8; SHA1_OUTPUT_SIZE];
+        for (chunk, s) in digest.chunks_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&s.to_be_bytes());
+        }
+        digest
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1::new()
+    }
+}
+
+impl HashFunction for Sha1 {
+    const BLOCK_SIZE: usize = SHA1_BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = SHA1_OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Sha1::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha1::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Sha1::finalize(self).to_vec()
+    }
+}
+
+pub fn sha1(data: &[u8]) -> [u8; SHA1_OUTPUT_SIZE] {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+// Secret-prefix MAC, sha1(key || message).
+pub fn sha1_keyed_mac(key: &[u8], message: &[u8]) -> [u8; SHA1_OUTPUT_SIZE] {
+    let mut hasher = Sha1::new();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn verify_sha1_keyed_mac(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    sha1_keyed_mac(key, message)[..] == *mac
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set2::challenge11::random_bytes;
+    use super::*;
+
+    #[test]
+    fn test_sha1_vectors() {
+        assert_eq!("da39a3ee5e6b4b0d3255bfef95601890afd80709", hex::encode(sha1(b"")));
+        assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", hex::encode(sha1(b"abc")));
+        assert_eq!("84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+                   hex::encode(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")));
+        assert_eq!("34aa973cd4c4daa4f61eeb2bdbad27316534016f", hex::encode(sha1(&vec![b'a'; 1_000_000])));
+    }
+
+    #[test]
+    fn test_sha1_incremental_and_state() {
+        let data = random_bytes(300);
+        let mut hasher = Sha1::new();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(sha1(&data), hasher.finalize());
+        assert_eq!(sha1(&data).to_vec(), <Sha1 as HashFunction>::digest(&data));
+
+        // hashing the rest of a padded message from its digest gives the digest of the longer message
+        let glued = [&data[..], &sha1_padding(data.len() as u64)].concat();
+        assert_eq!(0, glued.len() % SHA1_BLOCK_SIZE);
+        let mut resumed = Sha1::from_state(Sha1::state_from_digest(&sha1(&data)), glued.len() as u64);
+        resumed.update(b"more");
+        assert_eq!(sha1(&[&glued[..], b"more"].concat()), resumed.finalize());
+    }
 
     #[test]
     fn run_challenge28() {
+        let key = random_bytes(16);
+        let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+        let mac = sha1_keyed_mac(&key, message);
 
+        assert!(verify_sha1_keyed_mac(&key, message, &mac));
+        assert!(!verify_sha1_keyed_mac(&key, b"comment1=cooking%20MCs;userdata=foo;admin=true", &mac));
+        assert!(!verify_sha1_keyed_mac(&random_bytes(16), message, &mac));
     }
 
-}
\ No newline at end of file
+}
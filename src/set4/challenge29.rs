@@ -1,14 +1,109 @@
+use std::ops::RangeInclusive;
 
+use crate::error::Error;
+use crate::set4::challenge28::{HashFunction, Sha1, sha1_padding, SHA1_OUTPUT_SIZE};
+
+
+// Merkle-Damgard hash whose internal state can be rebuilt from a digest, which is all a length extension needs.
+pub trait LengthExtendable: HashFunction {
+    // Glue padding the hash appends to a message of the given length.
+    fn padding(message_len: u64) -> Vec<u8>;
+
+    // Resume hashing from a digest, as if processed_len bytes (a whole number of blocks) had already been hashed.
+    fn from_digest(digest: &[u8], processed_len: u64) -> Result<Self, Error>;
+}
+
+impl LengthExtendable for Sha1 {
+    fn padding(message_len: u64) -> Vec<u8> {
+        sha1_padding(message_len)
+    }
+
+    fn from_digest(digest: &[u8], processed_len: u64) -> Result<Self, Error> {
+        let digest: &[u8; SHA1_OUTPUT_SIZE] = digest.try_into()
+            .map_err(|_| Error::InvalidFormat(format!("sha1 digest must be {} bytes", SHA1_OUTPUT_SIZE)))?;
+        Ok(Sha1::from_state(Sha1::state_from_digest(digest), processed_len))
+    }
+}
+
+// Secret-prefix MAC, hash(key || message).
+pub fn keyed_mac<H: HashFunction>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn verify_keyed_mac<H: HashFunction>(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    keyed_mac::<H>(key, message) == mac
+}
+
+// A forged message, its valid MAC and the key length that made it verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthExtension {
+    pub key_len: usize,
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+// Forge mac(key || message || glue padding || extension) from mac(key || message) without the key. The glue
+// padding depends on the key length, so each length in the range is tried against the verifier.
+pub fn forge_length_extension<H: LengthExtendable>(
+    message: &[u8],
+    mac: &[u8],
+    extension: &[u8],
+    key_lengths: RangeInclusive<usize>,
+    verify: impl Fn(&[u8], &[u8]) -> bool,
+) -> Result<LengthExtension, Error> {
+    for key_len in key_lengths {
+        let original_len = (key_len + message.len()) as u64;
+        let glue = H::padding(original_len);
+
+        let mut hasher = H::from_digest(mac, original_len + glue.len() as u64)?;
+        hasher.update(extension);
+        let forged_mac = hasher.finalize();
+        let forged_message = [message, &glue, extension].concat();
+
+        if verify(&forged_message, &forged_mac) {
+            return Ok(LengthExtension { key_len, message: forged_message, mac: forged_mac });
+        }
+    }
+
+    Err(Error::AttackFailed("no key length in the range produced a valid mac".to_owned()))
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use rand::Rng;
+    use crate::set2::challenge11::random_bytes;
+    use super::*;
+
+    const CHALLENGE29_MESSAGE: &str = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    const CHALLENGE29_EXTENSION: &str = ";admin=true";
+
+    #[test]
+    fn test_sha1_from_digest() {
+        assert!(Sha1::from_digest(&[0u8; 19], 64).is_err());
+        assert_eq!(keyed_mac::<Sha1>(b"key", b"message"), crate::set4::challenge28::sha1_keyed_mac(b"key", b"message").to_vec());
+    }
 
     #[test]
     fn run_challenge29() {
+        let key = random_bytes(rand::thread_rng().gen_range(1..=32));
+        let mac = keyed_mac::<Sha1>(&key, CHALLENGE29_MESSAGE.as_bytes());
+
+        let forged = forge_length_extension::<Sha1>(
+            CHALLENGE29_MESSAGE.as_bytes(),
+            &mac,
+            CHALLENGE29_EXTENSION.as_bytes(),
+            0..=64,
+            |message, mac| verify_keyed_mac::<Sha1>(&key, message, mac),
+        ).unwrap();
 
+        assert_eq!(key.len(), forged.key_len);
+        assert!(forged.message.ends_with(CHALLENGE29_EXTENSION.as_bytes()));
+        assert!(verify_keyed_mac::<Sha1>(&key, &forged.message, &forged.mac));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,361 @@
+use crate::error::Error;
+use crate::set4::challenge28::{buffer_blocks, md_padding, HashFunction};
+use crate::set4::challenge29::LengthExtendable;
 
 
+pub const MD4_BLOCK_SIZE: usize = 64;
+pub const MD4_OUTPUT_SIZE: usize = 16;
+pub const SHA256_BLOCK_SIZE: usize = 64;
+pub const SHA256_OUTPUT_SIZE: usize = 32;
+pub const SHA512_BLOCK_SIZE: usize = 128;
+pub const SHA512_OUTPUT_SIZE: usize = 64;
+
+const MD4_INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+const SHA256_INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const SHA512_INITIAL_STATE: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const SHA512_ROUND_CONSTANTS: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+fn digest_length_error(name: &str, output_size: usize) -> Error {
+    Error::InvalidFormat(format!("{} digest must be {} bytes", name, output_size))
+}
+
+// Boolean function, additive constant, word order and rotations of one MD4 round.
+type Md4Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+
+fn md4_compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut x = [0u32; 16];
+    for (i, word) in block.chunks(4).enumerate() {
+        x[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    let [mut a, mut b, mut c, mut d] = *state;
+    let rounds: [Md4Round; 3] = [
+        (f, 0, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], [3, 7, 11, 19]),
+        (g, 0x5a827999, [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15], [3, 5, 9, 13]),
+        (h, 0x6ed9eba1, [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15], [3, 9, 11, 15]),
+    ];
+    for (function, constant, order, shifts) in rounds {
+        for (i, k) in order.iter().enumerate() {
+            let t = a.wrapping_add(function(b, c, d)).wrapping_add(x[*k]).wrapping_add(constant).rotate_left(shifts[i % 4]);
+            (a, b, c, d) = (d, t, b, c);
+        }
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+// Incremental MD4, which unlike SHA-1 stores words and the message length little endian.
+#[derive(Clone)]
+pub struct Md4 {
+    state: [u32; 4],
+    buffer: Vec<u8>,
+    processed_len: u64,
+}
+
+impl Md4 {
+    pub fn from_state(state: [u32; 4], processed_len: u64) -> Md4 {
+        Md4 { state, buffer: Vec::with_capacity(MD4_BLOCK_SIZE), processed_len }
+    }
+}
+
+impl HashFunction for Md4 {
+    const BLOCK_SIZE: usize = MD4_BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = MD4_OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Md4::from_state(MD4_INITIAL_STATE, 0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.processed_len += data.len() as u64;
+        let state = &mut self.state;
+        buffer_blocks(&mut self.buffer, data, MD4_BLOCK_SIZE, |block| md4_compress(state, block));
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Md4::padding(self.processed_len);
+        self.update(&padding);
+        self.state.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+}
+
+impl LengthExtendable for Md4 {
+    fn padding(message_len: u64) -> Vec<u8> {
+        md_padding(message_len, MD4_BLOCK_SIZE, false)
+    }
+
+    fn from_digest(digest: &[u8], processed_len: u64) -> Result<Self, Error> {
+        if digest.len() != MD4_OUTPUT_SIZE {
+            return Err(digest_length_error("md4", MD4_OUTPUT_SIZE));
+        }
+        let mut state = [0u32; 4];
+        for (s, word) in state.iter_mut().zip(digest.chunks(4)) {
+            *s = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        Ok(Md4::from_state(state, processed_len))
+    }
+}
+
+fn sha256_compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (k, wi) in SHA256_ROUND_CONSTANTS.iter().zip(w) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*k).wrapping_add(wi);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+
+        (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(temp1), c, b, a, temp1.wrapping_add(temp2));
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    processed_len: u64,
+}
+
+impl Sha256 {
+    pub fn from_state(state: [u32; 8], processed_len: u64) -> Sha256 {
+        Sha256 { state, buffer: Vec::with_capacity(SHA256_BLOCK_SIZE), processed_len }
+    }
+}
+
+impl HashFunction for Sha256 {
+    const BLOCK_SIZE: usize = SHA256_BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = SHA256_OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Sha256::from_state(SHA256_INITIAL_STATE, 0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.processed_len += data.len() as u64;
+        let state = &mut self.state;
+        buffer_blocks(&mut self.buffer, data, SHA256_BLOCK_SIZE, |block| sha256_compress(state, block));
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Sha256::padding(self.processed_len);
+        self.update(&padding);
+        self.state.iter().flat_map(|s| s.to_be_bytes()).collect()
+    }
+}
+
+impl LengthExtendable for Sha256 {
+    fn padding(message_len: u64) -> Vec<u8> {
+        md_padding(message_len, SHA256_BLOCK_SIZE, true)
+    }
+
+    fn from_digest(digest: &[u8], processed_len: u64) -> Result<Self, Error> {
+        if digest.len() != SHA256_OUTPUT_SIZE {
+            return Err(digest_length_error("sha256", SHA256_OUTPUT_SIZE));
+        }
+        let mut state = [0u32; 8];
+        for (s, word) in state.iter_mut().zip(digest.chunks(4)) {
+            *s = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        Ok(Sha256::from_state(state, processed_len))
+    }
+}
+
+fn sha512_compress(state: &mut [u64; 8], block: &[u8]) {
+    let mut w = [0u64; 80];
+    for (i, word) in block.chunks(8).enumerate() {
+        w[i] = u64::from_be_bytes(word.try_into().unwrap());
+    }
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (k, wi) in SHA512_ROUND_CONSTANTS.iter().zip(w) {
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*k).wrapping_add(wi);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+
+        (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(temp1), c, b, a, temp1.wrapping_add(temp2));
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+// SHA-512 works on 128 byte blocks and 64-bit words, its padding ends in a 128-bit length.
+#[derive(Clone)]
+pub struct Sha512 {
+    state: [u64; 8],
+    buffer: Vec<u8>,
+    processed_len: u64,
+}
+
+impl Sha512 {
+    pub fn from_state(state: [u64; 8], processed_len: u64) -> Sha512 {
+        Sha512 { state, buffer: Vec::with_capacity(SHA512_BLOCK_SIZE), processed_len }
+    }
+}
+
+impl HashFunction for Sha512 {
+    const BLOCK_SIZE: usize = SHA512_BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = SHA512_OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Sha512::from_state(SHA512_INITIAL_STATE, 0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.processed_len += data.len() as u64;
+        let state = &mut self.state;
+        buffer_blocks(&mut self.buffer, data, SHA512_BLOCK_SIZE, |block| sha512_compress(state, block));
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Sha512::padding(self.processed_len);
+        self.update(&padding);
+        self.state.iter().flat_map(|s| s.to_be_bytes()).collect()
+    }
+}
+
+impl LengthExtendable for Sha512 {
+    fn padding(message_len: u64) -> Vec<u8> {
+        md_padding(message_len, SHA512_BLOCK_SIZE, true)
+    }
+
+    fn from_digest(digest: &[u8], processed_len: u64) -> Result<Self, Error> {
+        if digest.len() != SHA512_OUTPUT_SIZE {
+            return Err(digest_length_error("sha512", SHA512_OUTPUT_SIZE));
+        }
+        let mut state = [0u64; 8];
+        for (s, word) in state.iter_mut().zip(digest.chunks(8)) {
+            *s = u64::from_be_bytes(word.try_into().unwrap());
+        }
+        Ok(Sha512::from_state(state, processed_len))
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use rand::Rng;
+    use crate::set2::challenge11::random_bytes;
+    use crate::set4::challenge29::{forge_length_extension, keyed_mac, verify_keyed_mac};
+    use super::*;
 
     #[test]
-    fn run_challenge30() {
+    fn test_md4_vectors() {
+        // RFC 1320
+        assert_eq!("31d6cfe0d16ae931b73c59d7e0c089c0", hex::encode(Md4::digest(b"")));
+        assert_eq!("a448017aaf21d8525fc10ae87aa6729d", hex::encode(Md4::digest(b"abc")));
+        assert_eq!("d79e1c308aa5bbcdeea8ed63df412da9", hex::encode(Md4::digest(b"abcdefghijklmnopqrstuvwxyz")));
+        assert_eq!("e33b4ddc9c38f2199c3e7b164fcc0536",
+                   hex::encode(Md4::digest(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890")));
+    }
+
+    #[test]
+    fn test_sha2_vectors() {
+        assert_eq!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", hex::encode(Sha256::digest(b"")));
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", hex::encode(Sha256::digest(b"abc")));
+        assert_eq!("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                   hex::encode(Sha256::digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")));
 
+        assert_eq!("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+                   hex::encode(Sha512::digest(b"abc")));
+        assert_eq!("8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+                   hex::encode(Sha512::digest(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu")));
+    }
+
+    fn check_length_extension<H: LengthExtendable>() {
+        let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+        let key = random_bytes(rand::thread_rng().gen_range(1..=32));
+        let mac = keyed_mac::<H>(&key, message);
+        assert_eq!(H::OUTPUT_SIZE, mac.len());
+        assert_eq!(H::BLOCK_SIZE, H::padding(0).len());
+
+        let forged = forge_length_extension::<H>(message, &mac, b";admin=true", 0..=64,
+            |message, mac| verify_keyed_mac::<H>(&key, message, mac)).unwrap();
+
+        assert_eq!(key.len(), forged.key_len);
+        assert!(H::from_digest(&mac[1..], 0).is_err());
+    }
+
+    #[test]
+    fn test_sha2_length_extension() {
+        check_length_extension::<Sha256>();
+        check_length_extension::<Sha512>();
+    }
+
+    #[test]
+    fn run_challenge30() {
+        check_length_extension::<Md4>();
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,328 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use crate::error::Error;
+use crate::set1::challenge1::hex_to_bytes;
+use crate::set4::challenge28::{HashFunction, Sha1};
+
+
+const HMAC_INNER_PAD: u8 = 0x36;
+const HMAC_OUTER_PAD: u8 = 0x5c;
+
+// HMAC (RFC 2104) over any of the crate's hash functions.
+pub fn hmac<H: HashFunction>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block_key = if key.len() > H::BLOCK_SIZE { H::digest(key) } else { key.to_vec() };
+    block_key.resize(H::BLOCK_SIZE, 0);
+
+    let mut inner = H::new();
+    inner.update(&block_key.iter().map(|k| k ^ HMAC_INNER_PAD).collect::<Vec<u8>>());
+    inner.update(message);
+
+    let mut outer = H::new();
+    outer.update(&block_key.iter().map(|k| k ^ HMAC_OUTER_PAD).collect::<Vec<u8>>());
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+// Byte at a time comparison with an early exit, sleeping after every matching byte.
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+    true
+}
+
+// Value of a query string parameter, the values used here never need percent decoding.
+fn query_parameter<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+// HTTP server on the loopback interface answering GET /test?file=...&signature=... with 200 when the signature
+// is the HMAC-SHA1 of the file name (truncated to signature_len bytes) and 500 otherwise.
+pub struct TimingServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TimingServer {
+    pub fn start(key: &[u8], delay: Duration, signature_len: usize) -> io::Result<TimingServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let key = key.to_vec();
+        let stopped = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    // a client hanging up early only affects its own request
+                    let _ = TimingServer::handle(stream, &key, delay, signature_len);
+                }
+            }
+        });
+
+        Ok(TimingServer { addr, stop, handle: Some(handle) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn handle(mut stream: TcpStream, key: &[u8], delay: Duration, signature_len: usize) -> io::Result<()> {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        let request = String::from_utf8_lossy(&request);
+        let target = request.lines().next().and_then(|line| line.strip_prefix("GET ")).and_then(|line| line.split(' ').next());
+
+        let status = match target.and_then(|target| target.strip_prefix("/test?")) {
+            None => "404 Not Found",
+            Some(query) => match (query_parameter(query, "file"), query_parameter(query, "signature").map(hex_to_bytes)) {
+                (Some(file), Some(Ok(signature))) => {
+                    let expected = hmac::<Sha1>(key, file.as_bytes());
+                    let expected = &expected[..signature_len.min(expected.len())];
+                    if insecure_compare(&signature, expected, delay) { "200 OK" } else { "500 Internal Server Error" }
+                }
+                _ => "400 Bad Request",
+            },
+        };
+
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status)
+    }
+}
+
+impl Drop for TimingServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake up the accept loop so it sees the stop flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Send a GET request and return the response status code.
+pub fn http_get(addr: SocketAddr, path: &str) -> io::Result<u16> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, addr)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    response.split(' ').nth(1).and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed status line"))
+}
+
+// Submit a signature for the file and time how long the server took to answer.
+pub fn timed_signature_query(addr: SocketAddr, file: &str, signature: &[u8]) -> io::Result<(bool, Duration)> {
+    let path = format!("/test?file={}&signature={}", file, hex::encode(signature));
+    let start = Instant::now();
+    let status = http_get(addr, &path)?;
+    Ok((status == 200, start.elapsed()))
+}
+
+// How repeated timing samples are summarised into one figure per candidate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Estimator {
+    // Lower median, so an even number of samples is not pulled up by a single slow outlier.
+    Median,
+    // Mean after discarding the given fraction of samples at each end.
+    TrimmedMean(f64),
+}
+
+impl Estimator {
+    pub fn estimate(&self, samples: &[Duration]) -> Duration {
+        if samples.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+
+        match self {
+            Estimator::Median => sorted[(sorted.len() - 1) / 2],
+            Estimator::TrimmedMean(fraction) => {
+                let trim = ((sorted.len() as f64 * fraction.clamp(0.0, 0.5)) as usize).min((sorted.len() - 1) / 2);
+                let kept = &sorted[trim..sorted.len() - trim];
+                kept.iter().sum::<Duration>() / kept.len() as u32
+            }
+        }
+    }
+}
+
+// Number of samples per candidate in a round, how to summarise them, how many rounds to try before settling on a
+// byte, and how many times to back off and re-time bytes when the recovered prefix turns out to be wrong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingAttackConfig {
+    pub samples: usize,
+    pub estimator: Estimator,
+    pub max_rounds: usize,
+    pub max_retries: usize,
+}
+
+
+// Time every candidate for one byte of the signature and return the slowest. Rounds of samples are added until
+// two rounds in a row agree on the winner and the winner stands clear of the runner-up: the right byte is one
+// delay above the bulk of the candidates, while a jitter spike usually leaves the right byte close behind.
+fn slowest_candidate(
+    known: &[u8],
+    mac_len: usize,
+    config: &TimingAttackConfig,
+    query: &mut impl FnMut(&[u8]) -> io::Result<(bool, Duration)>,
+) -> Result<u8, Error> {
+    let mut timings = vec![Vec::new(); 256];
+    let mut previous_winner = None;
+
+    for _ in 0..config.max_rounds {
+        for (candidate, samples) in timings.iter_mut().enumerate() {
+            let mut signature = known.to_vec();
+            signature.push(candidate as u8);
+            signature.resize(mac_len, 0);
+
+            for _ in 0..config.samples {
+                let (_, elapsed) = query(&signature).map_err(|e| Error::AttackFailed(e.to_string()))?;
+                samples.push(elapsed);
+            }
+        }
+
+        let mut estimates: Vec<(Duration, u8)> = timings.iter().enumerate()
+            .map(|(candidate, samples)| (config.estimator.estimate(samples), candidate as u8))
+            .collect();
+        estimates.sort();
+        let (best, winner) = estimates[255];
+        let (runner_up, _) = estimates[254];
+        let (typical, _) = estimates[128];
+
+        let clear = best - runner_up > (best - typical) / 2;
+        if clear && previous_winner == Some(winner) {
+            return Ok(winner);
+        }
+        previous_winner = Some(winner);
+    }
+
+    previous_winner.ok_or_else(|| Error::AttackFailed("no timing rounds were run".to_owned()))
+}
+
+// Recover a valid MAC one byte at a time: the right byte keeps the comparison going for one more delay. The last
+// byte adds no measurable delay, it is found by checking which candidate the server accepts. When no last byte
+// is accepted one of the timed bytes was wrong, so the attack backs off one more byte on every retry.
+pub fn recover_mac_by_timing(
+    mac_len: usize,
+    config: &TimingAttackConfig,
+    mut query: impl FnMut(&[u8]) -> io::Result<(bool, Duration)>,
+) -> Result<Vec<u8>, Error> {
+    if mac_len == 0 {
+        return Err(Error::AttackFailed("mac length must be at least one byte".to_owned()));
+    }
+
+    let mut known = Vec::with_capacity(mac_len);
+    for retry in 0..=config.max_retries {
+        while known.len() < mac_len - 1 {
+            let byte = slowest_candidate(&known, mac_len, config, &mut query)?;
+            known.push(byte);
+        }
+
+        for candidate in 0..=255u8 {
+            let signature = [&known[..], &[candidate]].concat();
+            let (accepted, _) = query(&signature).map_err(|e| Error::AttackFailed(e.to_string()))?;
+            if accepted {
+                return Ok(signature);
+            }
+        }
+
+        known.truncate(known.len().saturating_sub(retry + 1));
+    }
+
+    Err(Error::AttackFailed("no last byte completes any recovered prefix".to_owned()))
+}
+
+// A single sample per candidate is enough when the delay dwarfs the network jitter.
+pub fn single_sample_config() -> TimingAttackConfig {
+    TimingAttackConfig { samples: 1, estimator: Estimator::Median, max_rounds: 3, max_retries: 3 }
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set4::challenge30::{Sha256, Sha512};
+    use super::*;
+
+    #[test]
+    fn test_hmac_vectors() {
+        // RFC 2202 and RFC 4231 test cases 1 and 2
+        let key = [0x0b; 20];
+        assert_eq!("b617318655057264e28bc0b6fb378c8ef146be00", hex::encode(hmac::<Sha1>(&key, b"Hi There")));
+        assert_eq!("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7", hex::encode(hmac::<Sha256>(&key, b"Hi There")));
+        assert_eq!("87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+                   hex::encode(hmac::<Sha512>(&key, b"Hi There")));
+        assert_eq!("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79", hex::encode(hmac::<Sha1>(b"Jefe", b"what do ya want for nothing?")));
+
+        // keys longer than a block are hashed first
+        let long_key = [0xaa; 80];
+        assert_eq!("aa4ae5e15272d00e95705637ce8a3b55ed402112",
+                   hex::encode(hmac::<Sha1>(&long_key, b"Test Using Larger Than Block-Size Key - Hash Key First")));
+    }
 
     #[test]
+    fn test_estimators() {
+        let samples: Vec<Duration> = [5, 1, 100, 3, 2, 4, 6].iter().map(|&ms| Duration::from_millis(ms)).collect();
+
+        assert_eq!(Duration::from_millis(4), Estimator::Median.estimate(&samples));
+        // one sample trimmed at each end leaves 2..=6
+        assert_eq!(Duration::from_millis(4), Estimator::TrimmedMean(0.2).estimate(&samples));
+        assert_eq!(Duration::from_millis(121) / 7, Estimator::TrimmedMean(0.0).estimate(&samples));
+        assert_eq!(Duration::from_millis(3), Estimator::Median.estimate(&samples[1..]));
+        assert_eq!(Duration::ZERO, Estimator::Median.estimate(&[]));
+    }
+
+    #[test]
+    fn test_timing_server() {
+        let key = b"YELLOW SUBMARINE";
+        let server = TimingServer::start(key, Duration::ZERO, 20).unwrap();
+        let mac = hmac::<Sha1>(key, b"foo");
+
+        assert_eq!(200, http_get(server.addr(), &format!("/test?file=foo&signature={}", hex::encode(&mac))).unwrap());
+        assert_eq!(500, http_get(server.addr(), &format!("/test?file=bar&signature={}", hex::encode(&mac))).unwrap());
+        assert_eq!(400, http_get(server.addr(), "/test?file=foo&signature=zz").unwrap());
+        assert_eq!(404, http_get(server.addr(), "/").unwrap());
+    }
+
+    #[test]
+    #[ignore = "timing sensitive"]
     fn run_challenge31() {
+        // the full challenge uses 50ms and a 20 byte mac, the test keeps the same shape at a size that runs quickly
+        let key = b"YELLOW SUBMARINE";
+        let signature_len = 3;
+        let server = TimingServer::start(key, Duration::from_millis(3), signature_len).unwrap();
+
+        let recovered = recover_mac_by_timing(signature_len, &single_sample_config(),
+            |signature| timed_signature_query(server.addr(), "foo", signature)).unwrap();
 
+        assert_eq!(hmac::<Sha1>(key, b"foo")[..signature_len], recovered[..]);
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,26 @@
 
 
-
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use std::time::Duration;
+
+    use crate::set4::challenge28::Sha1;
+    use crate::set4::challenge31::{hmac, recover_mac_by_timing, timed_signature_query, Estimator, TimingAttackConfig, TimingServer};
 
     #[test]
+    #[ignore = "timing sensitive"]
     fn run_challenge32() {
+        // sub-millisecond delay, which needs several samples per candidate to stand out of the jitter
+        let key = b"YELLOW SUBMARINE";
+        let signature_len = 3;
+        let server = TimingServer::start(key, Duration::from_micros(500), signature_len).unwrap();
+        let config = TimingAttackConfig { samples: 5, estimator: Estimator::TrimmedMean(0.2), max_rounds: 5, max_retries: 5 };
+
+        let recovered = recover_mac_by_timing(signature_len, &config,
+            |signature| timed_signature_query(server.addr(), "foo", signature)).unwrap();
 
+        assert_eq!(hmac::<Sha1>(key, b"foo")[..signature_len], recovered[..]);
     }
 
-}
\ No newline at end of file
+}
@@ -1,9 +1,9 @@
-mod challenge25;
-mod challenge26;
-mod challenge27;
-mod challenge28;
-mod challenge29;
-mod challenge30;
-mod challenge31;
-mod challenge32;
+pub mod challenge25;
+pub mod challenge26;
+pub mod challenge27;
+pub mod challenge28;
+pub mod challenge29;
+pub mod challenge30;
+pub mod challenge31;
+pub mod challenge32;
 
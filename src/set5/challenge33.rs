@@ -1,14 +1,173 @@
+use crate::bignum::{random_range, BigUint, Montgomery};
+use crate::error::Error;
+use crate::set4::challenge28::sha1;
 
 
+// Safe primes of the standard finite field groups, all used with generator 2.
+// RFC 3526 MODP groups, the 1536-bit one is the "NIST" prime of the challenge.
+const MODP_1536_PRIME: &str = "\
+    ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff";
+
+const MODP_2048_PRIME: &str = "\
+    ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b\
+    e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718\
+    3995497cea956ae515d2261898fa051015728e5a8aacaa68ffffffffffffffff";
+
+const MODP_3072_PRIME: &str = "\
+    ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b\
+    e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718\
+    3995497cea956ae515d2261898fa051015728e5a8aaac42dad33170d04507a33\
+    a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7\
+    abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864\
+    d87602733ec86a64521f2b18177b200cbbe117577a615d6c770988c0bad946e2\
+    08e24fa074e5ab3143db5bfce0fd108e4b82d120a93ad2caffffffffffffffff";
+
+// RFC 7919 FFDHE groups.
+const FFDHE_2048_PRIME: &str = "\
+    ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+    a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+    d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+    984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+    bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+    ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+    9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+    c58ef1837d1683b2c6f34a26c1b2effa886b423861285c97ffffffffffffffff";
+
+const FFDHE_3072_PRIME: &str = "\
+    ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+    a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+    d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+    984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+    bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+    ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+    9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+    c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b\
+    bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c\
+    aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff\
+    5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e\
+    0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b66c62e37ffffffffffffffff";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedGroup {
+    Modp1536,
+    Modp2048,
+    Modp3072,
+    Ffdhe2048,
+    Ffdhe3072,
+}
+
+// Finite field Diffie-Hellman group, the modulus p and the generator g.
+// Every exponentiation is modulo p, so the Montgomery context for p is built once with the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhGroup {
+    pub p: BigUint,
+    pub g: BigUint,
+    montgomery: Montgomery,
+}
+
+#[derive(Debug, Clone)]
+pub struct DhKeyPair {
+    pub private: BigUint,
+    pub public: BigUint,
+}
+
+impl DhGroup {
+    // Custom group, p must be odd and above 3 so private keys can be drawn from [2, p - 1).
+    pub fn new(p: BigUint, g: BigUint) -> Result<DhGroup, Error> {
+        if p <= BigUint::from(3u32) {
+            return Err(Error::InvalidModulus(format!("dh modulus {} is too small", p)));
+        }
+        let montgomery = Montgomery::new(&p)?;
+        Ok(DhGroup { p, g, montgomery })
+    }
+
+    pub fn named(group: NamedGroup) -> DhGroup {
+        let prime = match group {
+            NamedGroup::Modp1536 => MODP_1536_PRIME,
+            NamedGroup::Modp2048 => MODP_2048_PRIME,
+            NamedGroup::Modp3072 => MODP_3072_PRIME,
+            NamedGroup::Ffdhe2048 => FFDHE_2048_PRIME,
+            NamedGroup::Ffdhe3072 => FFDHE_3072_PRIME,
+        };
+        // the constants are valid groups, so the checks of new cannot fail
+        DhGroup::new(BigUint::parse_bytes(prime.as_bytes(), 16).unwrap(), BigUint::from(2u32)).unwrap()
+    }
+
+    pub fn generate_keypair(&self) -> DhKeyPair {
+        let private = random_range(&BigUint::from(2u32), &(&self.p - 1u32));
+        let public = self.montgomery.pow(&self.g, &private);
+        DhKeyPair { private, public }
+    }
+
+    // The other side's public key is used as is, checking it is in range is left to the protocol.
+    pub fn shared_secret(&self, private: &BigUint, other_public: &BigUint) -> BigUint {
+        self.montgomery.pow(other_public, private)
+    }
+}
+
+// AES-128 key from the first 16 bytes of the SHA-1 of the shared secret in big endian.
+pub fn derive_session_key(shared_secret: &BigUint) -> Vec<u8> {
+    sha1(&shared_secret.to_bytes_be())[..16].to_vec()
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::bignum::modexp;
+    use super::*;
+
+    #[test]
+    fn test_small_group() {
+        let group = DhGroup::new(BigUint::from(37u32), BigUint::from(5u32)).unwrap();
+        let alice = group.generate_keypair();
+        let bob = group.generate_keypair();
+
+        assert!(alice.public < group.p);
+        assert_eq!(group.shared_secret(&alice.private, &bob.public), group.shared_secret(&bob.private, &alice.public));
+        assert!(DhGroup::new(BigUint::from(3u32), BigUint::from(2u32)).is_err());
+        assert!(DhGroup::new(BigUint::from(38u32), BigUint::from(5u32)).is_err());
+    }
+
+    #[test]
+    fn test_named_groups() {
+        for (group, bits) in [(NamedGroup::Modp1536, 1536), (NamedGroup::Modp2048, 2048), (NamedGroup::Modp3072, 3072),
+                              (NamedGroup::Ffdhe2048, 2048), (NamedGroup::Ffdhe3072, 3072)] {
+            let group = DhGroup::named(group);
+            assert_eq!(bits, group.p.bits());
+            // safe primes, so 2 passes the Fermat test for both p and q = (p - 1) / 2
+            let q = (&group.p - 1u32) >> 1;
+            assert_eq!(BigUint::from(1u32), modexp(&group.g, &(&group.p - 1u32), &group.p));
+            assert_eq!(BigUint::from(1u32), modexp(&group.g, &(&q - 1u32), &q));
+        }
+    }
 
     #[test]
     fn run_challenge33() {
+        let group = DhGroup::named(NamedGroup::Modp1536);
+        let alice = group.generate_keypair();
+        let bob = group.generate_keypair();
+
+        let alice_secret = group.shared_secret(&alice.private, &bob.public);
+        let bob_secret = group.shared_secret(&bob.private, &alice.public);
 
+        assert_eq!(alice_secret, bob_secret);
+        assert_eq!(16, derive_session_key(&alice_secret).len());
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,262 @@
+use crate::bignum::BigUint;
+use crate::error::Error;
+use crate::set1::challenge7::AES_BLOCK_SIZE;
+use crate::set2::challenge10::{aes_128_cbc_decrypt, aes_128_cbc_encrypt};
+use crate::set2::challenge11::random_bytes;
+use crate::set5::challenge33::{derive_session_key, DhGroup, DhKeyPair};
 
 
+// Messages of the echo protocol: Alice proposes a group, Bob acknowledges the group he will use, both send their
+// public keys, then Alice sends encrypted messages that Bob decrypts and echoes back re-encrypted under a fresh IV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Negotiate { p: BigUint, g: BigUint },
+    Ack { p: BigUint, g: BigUint },
+    PublicKey(BigUint),
+    Encrypted { ciphertext: Vec<u8>, iv: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    AliceToBob,
+    BobToAlice,
+}
+
+// Whatever sits on the wire between Alice and Bob, it sees every message and decides what gets delivered.
+pub trait Channel {
+    fn transmit(&mut self, direction: Direction, message: Message) -> Message;
+}
+
+// Honest network that delivers messages unchanged and keeps a transcript.
+#[derive(Default)]
+pub struct DirectChannel {
+    pub transcript: Vec<(Direction, Message)>,
+}
+
+impl Channel for DirectChannel {
+    fn transmit(&mut self, direction: Direction, message: Message) -> Message {
+        self.transcript.push((direction, message.clone()));
+        message
+    }
+}
+
+pub fn encrypt_payload(key: &[u8], plaintext: &[u8]) -> Message {
+    let iv = random_bytes(AES_BLOCK_SIZE);
+    let ciphertext = aes_128_cbc_encrypt(key, &iv, plaintext).unwrap();
+    Message::Encrypted { ciphertext, iv }
+}
+
+fn unexpected(actor: &str, message: &Message) -> Error {
+    Error::InvalidFormat(format!("{} did not expect {:?}", actor, message))
+}
+
+// Initiator, sends its messages one at a time and checks each echo.
+pub struct Alice {
+    group: DhGroup,
+    messages: Vec<Vec<u8>>,
+    keypair: Option<DhKeyPair>,
+    session_key: Option<Vec<u8>>,
+    sent: usize,
+    echoes: Vec<Vec<u8>>,
+}
+
+impl Alice {
+    pub fn new(group: DhGroup, messages: Vec<Vec<u8>>) -> Alice {
+        Alice { group, messages, keypair: None, session_key: None, sent: 0, echoes: Vec::new() }
+    }
+
+    pub fn start(&self) -> Message {
+        Message::Negotiate { p: self.group.p.clone(), g: self.group.g.clone() }
+    }
+
+    // Echoed plaintexts received so far.
+    pub fn echoes(&self) -> &[Vec<u8>] {
+        &self.echoes
+    }
+
+    fn next_payload(&mut self, key: &[u8]) -> Option<Message> {
+        let message = self.messages.get(self.sent)?;
+        self.sent += 1;
+        Some(encrypt_payload(key, message))
+    }
+
+    // Reply to a message from Bob, None once every message has been echoed.
+    pub fn receive(&mut self, message: Message) -> Result<Option<Message>, Error> {
+        match (message, &self.keypair, &self.session_key) {
+            (Message::Ack { p, g }, None, _) => {
+                // Alice goes with the group Bob acknowledged
+                self.group = DhGroup::new(p, g)?;
+                let keypair = self.group.generate_keypair();
+                let reply = Message::PublicKey(keypair.public.clone());
+                self.keypair = Some(keypair);
+                Ok(Some(reply))
+            }
+            (Message::PublicKey(bob_public), Some(keypair), None) => {
+                let key = derive_session_key(&self.group.shared_secret(&keypair.private, &bob_public));
+                let reply = self.next_payload(&key);
+                self.session_key = Some(key);
+                Ok(reply)
+            }
+            (Message::Encrypted { ciphertext, iv }, _, Some(key)) => {
+                let key = key.clone();
+                let Some(outstanding) = self.sent.checked_sub(1).and_then(|i| self.messages.get(i)) else {
+                    return Err(Error::InvalidFormat("alice has no message awaiting an echo".to_owned()));
+                };
+                let echo = aes_128_cbc_decrypt(&key, &iv, &ciphertext)?;
+                if echo != *outstanding {
+                    return Err(Error::InvalidFormat("echo does not match the message sent".to_owned()));
+                }
+                self.echoes.push(echo);
+                Ok(self.next_payload(&key))
+            }
+            (message, _, _) => Err(unexpected("alice", &message)),
+        }
+    }
+}
+
+// Responder, accepts the proposed group and echoes every message it can decrypt.
+#[derive(Default)]
+pub struct Bob {
+    group: Option<DhGroup>,
+    session_key: Option<Vec<u8>>,
+    received: Vec<Vec<u8>>,
+}
+
+impl Bob {
+    pub fn new() -> Bob {
+        Bob::default()
+    }
+
+    pub fn received(&self) -> &[Vec<u8>] {
+        &self.received
+    }
+
+    pub fn receive(&mut self, message: Message) -> Result<Option<Message>, Error> {
+        match (message, &self.group, &self.session_key) {
+            (Message::Negotiate { p, g }, None, _) => {
+                let group = DhGroup::new(p, g)?;
+                let reply = Message::Ack { p: group.p.clone(), g: group.g.clone() };
+                self.group = Some(group);
+                Ok(Some(reply))
+            }
+            (Message::PublicKey(alice_public), Some(group), None) => {
+                let keypair = group.generate_keypair();
+                self.session_key = Some(derive_session_key(&group.shared_secret(&keypair.private, &alice_public)));
+                Ok(Some(Message::PublicKey(keypair.public)))
+            }
+            (Message::Encrypted { ciphertext, iv }, _, Some(key)) => {
+                let plaintext = aes_128_cbc_decrypt(key, &iv, &ciphertext)?;
+                let reply = encrypt_payload(key, &plaintext);
+                self.received.push(plaintext);
+                Ok(Some(reply))
+            }
+            (message, _, _) => Err(unexpected("bob", &message)),
+        }
+    }
+}
+
+// Run the protocol to completion over the channel, each message goes through the channel before delivery.
+pub fn run_protocol(alice: &mut Alice, bob: &mut Bob, channel: &mut impl Channel) -> Result<(), Error> {
+    let mut message = alice.start();
+    loop {
+        let delivered = channel.transmit(Direction::AliceToBob, message);
+        let Some(reply) = bob.receive(delivered)? else {
+            return Ok(());
+        };
+
+        let delivered = channel.transmit(Direction::BobToAlice, reply);
+        match alice.receive(delivered)? {
+            Some(next) => message = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+// Key-fixing attack: Mallory swaps both public keys for p, so both sides compute a shared secret of p^x mod p = 0
+// and Mallory can decrypt every payload while relaying it unchanged.
+#[derive(Default)]
+pub struct KeyFixingMallory {
+    p: Option<BigUint>,
+    pub recovered: Vec<Vec<u8>>,
+}
+
+impl KeyFixingMallory {
+    pub fn new() -> KeyFixingMallory {
+        KeyFixingMallory::default()
+    }
+}
+
+impl Channel for KeyFixingMallory {
+    fn transmit(&mut self, _direction: Direction, message: Message) -> Message {
+        match message {
+            Message::Negotiate { ref p, .. } => {
+                self.p = Some(p.clone());
+                message
+            }
+            Message::PublicKey(_) => Message::PublicKey(self.p.clone().unwrap_or_default()),
+            Message::Encrypted { ref ciphertext, ref iv } => {
+                let key = derive_session_key(&BigUint::default());
+                if let Ok(plaintext) = aes_128_cbc_decrypt(&key, iv, ciphertext) {
+                    self.recovered.push(plaintext);
+                }
+                message
+            }
+            Message::Ack { .. } => message,
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge33::NamedGroup;
+    use super::*;
+
+    fn messages() -> Vec<Vec<u8>> {
+        vec![b"Hello Bob".to_vec(), b"The eagle lands at midnight, bring the YELLOW SUBMARINE".to_vec()]
+    }
+
+    #[test]
+    fn test_honest_protocol() {
+        let mut alice = Alice::new(DhGroup::named(NamedGroup::Modp1536), messages());
+        let mut bob = Bob::new();
+        let mut channel = DirectChannel::default();
+
+        run_protocol(&mut alice, &mut bob, &mut channel).unwrap();
+
+        assert_eq!(messages(), alice.echoes());
+        assert_eq!(messages(), bob.received());
+        // negotiate, ack, two public keys, then a payload and its echo per message
+        assert_eq!(8, channel.transcript.len());
+        assert!(bob.receive(Message::PublicKey(BigUint::from(2u32))).is_err());
+    }
+
+    #[test]
+    fn test_echo_without_outstanding_message() {
+        let group = DhGroup::named(NamedGroup::Modp1536);
+        let mut alice = Alice::new(group.clone(), Vec::new());
+        let bob = group.generate_keypair();
+
+        alice.receive(Message::Ack { p: group.p.clone(), g: group.g.clone() }).unwrap();
+        // with nothing to send the handshake ends without a payload, but the session key is set
+        assert_eq!(Ok(None), alice.receive(Message::PublicKey(bob.public)));
+
+        let injected = encrypt_payload(&[0u8; 16], b"injected by Mallory");
+        assert!(matches!(alice.receive(injected), Err(Error::InvalidFormat(_))));
+    }
 
     #[test]
     fn run_challenge34() {
+        let mut alice = Alice::new(DhGroup::named(NamedGroup::Modp1536), messages());
+        let mut bob = Bob::new();
+        let mut mallory = KeyFixingMallory::new();
+
+        run_protocol(&mut alice, &mut bob, &mut mallory).unwrap();
 
+        // the protocol still completes, and Mallory has read both directions
+        assert_eq!(messages(), alice.echoes());
+        assert_eq!(vec![messages()[0].clone(), messages()[0].clone(), messages()[1].clone(), messages()[1].clone()], mallory.recovered);
     }
 
-}
\ No newline at end of file
+}
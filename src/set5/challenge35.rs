@@ -1,14 +1,102 @@
+use crate::bignum::BigUint;
+use crate::set2::challenge10::aes_128_cbc_decrypt;
+use crate::set5::challenge33::derive_session_key;
+use crate::set5::challenge34::{Channel, Direction, Message};
 
 
+// Generators Mallory can substitute during negotiation, each pins the shared secret to a few known values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaliciousG {
+    // every power of 1 is 1
+    One,
+    // every power of p is 0 mod p
+    P,
+    // powers of p - 1 alternate between 1 and p - 1
+    PMinusOne,
+}
+
+impl MaliciousG {
+    pub fn generator(&self, p: &BigUint) -> BigUint {
+        match self {
+            MaliciousG::One => BigUint::from(1u32),
+            MaliciousG::P => p.clone(),
+            MaliciousG::PMinusOne => p - 1u32,
+        }
+    }
+
+    pub fn candidate_secrets(&self, p: &BigUint) -> Vec<BigUint> {
+        match self {
+            MaliciousG::One => vec![BigUint::from(1u32)],
+            MaliciousG::P => vec![BigUint::from(0u32)],
+            MaliciousG::PMinusOne => vec![BigUint::from(1u32), p - 1u32],
+        }
+    }
+}
+
+// Rewrites the proposed generator before it reaches Bob, whose acknowledgement then makes Alice use it too, and
+// decrypts the payloads by trying each possible shared secret.
+pub struct MaliciousGMallory {
+    g: MaliciousG,
+    p: Option<BigUint>,
+    pub recovered: Vec<Vec<u8>>,
+}
+
+impl MaliciousGMallory {
+    pub fn new(g: MaliciousG) -> MaliciousGMallory {
+        MaliciousGMallory { g, p: None, recovered: Vec::new() }
+    }
+}
+
+impl Channel for MaliciousGMallory {
+    fn transmit(&mut self, direction: Direction, message: Message) -> Message {
+        match (direction, message) {
+            (Direction::AliceToBob, Message::Negotiate { p, .. }) => {
+                let g = self.g.generator(&p);
+                self.p = Some(p.clone());
+                Message::Negotiate { p, g }
+            }
+            (_, Message::Encrypted { ciphertext, iv }) => {
+                let p = self.p.clone().unwrap_or_default();
+                // a wrong key rarely yields valid padding, and practically never valid padding over ascii text
+                let plaintext = self.g.candidate_secrets(&p).iter()
+                    .filter_map(|secret| aes_128_cbc_decrypt(&derive_session_key(secret), &iv, &ciphertext).ok())
+                    .find(|plaintext| plaintext.is_ascii());
+                if let Some(plaintext) = plaintext {
+                    self.recovered.push(plaintext);
+                }
+                Message::Encrypted { ciphertext, iv }
+            }
+            (_, message) => message,
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge33::{DhGroup, NamedGroup};
+    use crate::set5::challenge34::{run_protocol, Alice, Bob};
+    use super::*;
 
     #[test]
     fn run_challenge35() {
+        let messages = vec![b"Hello Bob".to_vec(), b"Meet me by the YELLOW SUBMARINE".to_vec()];
+
+        for g in [MaliciousG::One, MaliciousG::P, MaliciousG::PMinusOne] {
+            // several runs so that both parities of the private keys show up for p - 1
+            for _ in 0..4 {
+                let mut alice = Alice::new(DhGroup::named(NamedGroup::Modp1536), messages.clone());
+                let mut bob = Bob::new();
+                let mut mallory = MaliciousGMallory::new(g);
+
+                run_protocol(&mut alice, &mut bob, &mut mallory).unwrap();
 
+                assert_eq!(messages, alice.echoes());
+                assert_eq!(4, mallory.recovered.len(), "{:?}", g);
+                assert_eq!(messages[1], mallory.recovered[3]);
+            }
+        }
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,188 @@
+use std::collections::HashMap;
 
+use num_traits::Zero;
+
+use crate::bignum::{modexp, random_below, BigUint};
+use crate::error::Error;
+use crate::set2::challenge11::random_bytes;
+use crate::set4::challenge28::HashFunction;
+use crate::set4::challenge30::Sha256;
+use crate::set4::challenge31::hmac;
+use crate::set5::challenge33::{DhGroup, NamedGroup};
+
+
+pub const SRP_SALT_LEN: usize = 16;
+
+// Group and multiplier shared by client and server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrpParams {
+    pub n: BigUint,
+    pub g: BigUint,
+    pub k: BigUint,
+}
+
+impl Default for SrpParams {
+    // The NIST prime with g = 2 and k = 3, as in the challenge.
+    fn default() -> Self {
+        let group = DhGroup::named(NamedGroup::Modp1536);
+        SrpParams { n: group.p, g: group.g, k: BigUint::from(3u32) }
+    }
+}
+
+// SHA-256 of the concatenated parts, read as a big endian integer.
+pub fn hash_to_int(parts: &[&[u8]]) -> BigUint {
+    BigUint::from_bytes_be(&Sha256::digest(&parts.concat()))
+}
+
+// Private exponent derived from the salt and the password.
+pub fn password_exponent(salt: &[u8], password: &str) -> BigUint {
+    hash_to_int(&[salt, password.as_bytes()])
+}
+
+// Session key from the shared secret S.
+pub fn srp_session_key(shared_secret: &BigUint) -> Vec<u8> {
+    Sha256::digest(&shared_secret.to_bytes_be())
+}
+
+// What the client sends to prove it knows the session key.
+pub fn srp_proof(session_key: &[u8], salt: &[u8]) -> Vec<u8> {
+    hmac::<Sha256>(session_key, salt)
+}
+
+// The server never stores the password, only the salt and v = g^x.
+#[derive(Debug, Clone)]
+pub struct SrpVerifier {
+    pub salt: Vec<u8>,
+    pub v: BigUint,
+}
+
+struct SrpSession {
+    a_public: BigUint,
+    b_private: BigUint,
+    b_public: BigUint,
+}
+
+pub struct SrpServer {
+    params: SrpParams,
+    check_public_keys: bool,
+    verifiers: HashMap<String, SrpVerifier>,
+    sessions: HashMap<String, SrpSession>,
+}
+
+impl SrpServer {
+    // Textbook server that trusts the client's public key.
+    pub fn new(params: SrpParams) -> SrpServer {
+        SrpServer { params, check_public_keys: false, verifiers: HashMap::new(), sessions: HashMap::new() }
+    }
+
+    // Abort logins whose A is 0 mod N, as SRP-6a requires.
+    pub fn with_public_key_check(mut self) -> SrpServer {
+        self.check_public_keys = true;
+        self
+    }
+
+    pub fn register(&mut self, email: &str, password: &str) {
+        let salt = random_bytes(SRP_SALT_LEN);
+        let v = modexp(&self.params.g, &password_exponent(&salt, password), &self.params.n);
+        self.verifiers.insert(email.to_owned(), SrpVerifier { salt, v });
+    }
+
+    pub fn verifier(&self, email: &str) -> Option<&SrpVerifier> {
+        self.verifiers.get(email)
+    }
+
+    // First round of a login, answers the client's A with the salt and B = kv + g^b.
+    pub fn start_login(&mut self, email: &str, a_public: &BigUint) -> Result<(Vec<u8>, BigUint), Error> {
+        let verifier = self.verifiers.get(email)
+            .ok_or_else(|| Error::InvalidFormat(format!("unknown user {}", email)))?;
+        let n = &self.params.n;
+        if self.check_public_keys && (a_public % n).is_zero() {
+            return Err(Error::InvalidFormat("client public key is 0 mod N".to_owned()));
+        }
+
+        let b_private = random_below(n);
+        let b_public = (&self.params.k * &verifier.v + modexp(&self.params.g, &b_private, n)) % n;
+        self.sessions.insert(email.to_owned(), SrpSession { a_public: a_public.clone(), b_private, b_public: b_public.clone() });
+        Ok((verifier.salt.clone(), b_public))
+    }
+
+    // Second round, S = (A * v^u)^b and the proof must be the HMAC of the salt under H(S).
+    pub fn finish_login(&mut self, email: &str, proof: &[u8]) -> Result<bool, Error> {
+        let session = self.sessions.remove(email)
+            .ok_or_else(|| Error::InvalidFormat(format!("no login in progress for {}", email)))?;
+        let verifier = &self.verifiers[email];
+        let n = &self.params.n;
+
+        let u = hash_to_int(&[&session.a_public.to_bytes_be(), &session.b_public.to_bytes_be()]);
+        let shared_secret = modexp(&(&session.a_public * modexp(&verifier.v, &u, n)), &session.b_private, n);
+        Ok(srp_proof(&srp_session_key(&shared_secret), &verifier.salt) == proof)
+    }
+}
+
+pub struct SrpClient {
+    params: SrpParams,
+    password: String,
+    a_private: BigUint,
+    a_public: BigUint,
+}
+
+impl SrpClient {
+    pub fn new(params: SrpParams, password: &str) -> SrpClient {
+        let a_private = random_below(&params.n);
+        let a_public = modexp(&params.g, &a_private, &params.n);
+        SrpClient { params, password: password.to_owned(), a_private, a_public }
+    }
+
+    pub fn public_key(&self) -> &BigUint {
+        &self.a_public
+    }
+
+    // S = (B - k * g^x)^(a + u * x)
+    pub fn proof(&self, salt: &[u8], b_public: &BigUint) -> Vec<u8> {
+        let n = &self.params.n;
+        let u = hash_to_int(&[&self.a_public.to_bytes_be(), &b_public.to_bytes_be()]);
+        let x = password_exponent(salt, &self.password);
+
+        let blinding = (&self.params.k * modexp(&self.params.g, &x, n)) % n;
+        let base = (b_public + n - blinding) % n;
+        let shared_secret = modexp(&base, &(&self.a_private + u * x), n);
+        srp_proof(&srp_session_key(&shared_secret), salt)
+    }
+}
+
+// Full login of the client against the server.
+pub fn srp_login(server: &mut SrpServer, email: &str, client: &SrpClient) -> Result<bool, Error> {
+    let (salt, b_public) = server.start_login(email, client.public_key())?;
+    server.finish_login(email, &client.proof(&salt, &b_public))
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_unknown_user_and_session() {
+        let mut server = SrpServer::new(SrpParams::default());
+        assert!(server.start_login("nobody@example.com", &BigUint::from(2u32)).is_err());
+        assert!(server.finish_login("nobody@example.com", b"proof").is_err());
+    }
 
     #[test]
     fn run_challenge36() {
+        let params = SrpParams::default();
+        let mut server = SrpServer::new(params.clone());
+        server.register("alice@example.com", "correct horse battery staple");
+
+        let verifier = server.verifier("alice@example.com").unwrap();
+        assert_eq!(SRP_SALT_LEN, verifier.salt.len());
+
+        let client = SrpClient::new(params.clone(), "correct horse battery staple");
+        assert_eq!(Ok(true), srp_login(&mut server, "alice@example.com", &client));
 
+        let impostor = SrpClient::new(params, "password1");
+        assert_eq!(Ok(false), srp_login(&mut server, "alice@example.com", &impostor));
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,45 @@
+use crate::bignum::BigUint;
+use crate::error::Error;
+use crate::set5::challenge36::{srp_proof, srp_session_key, SrpServer};
 
 
+// Log in without the password by sending A = 0 mod N: the server's S = (A * v^u)^b is then 0, so the proof is
+// the HMAC of the salt under H(0). Any multiple of N works, such as 0, N or 2N.
+pub fn login_with_zero_key(server: &mut SrpServer, email: &str, a_public: &BigUint) -> Result<bool, Error> {
+    let (salt, _) = server.start_login(email, a_public)?;
+    let session_key = srp_session_key(&BigUint::from(0u32));
+    server.finish_login(email, &srp_proof(&session_key, &salt))
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge36::SrpParams;
+    use super::*;
 
     #[test]
     fn run_challenge37() {
+        let params = SrpParams::default();
+        let mut server = SrpServer::new(params.clone());
+        server.register("alice@example.com", "correct horse battery staple");
+
+        for multiple in 0u32..=2 {
+            let a_public = &params.n * multiple;
+            assert_eq!(Ok(true), login_with_zero_key(&mut server, "alice@example.com", &a_public));
+        }
+    }
+
+    #[test]
+    fn test_public_key_check_rejects_zero_key() {
+        let params = SrpParams::default();
+        let mut server = SrpServer::new(params.clone()).with_public_key_check();
+        server.register("alice@example.com", "correct horse battery staple");
 
+        for multiple in 0u32..=2 {
+            let a_public = &params.n * multiple;
+            assert!(login_with_zero_key(&mut server, "alice@example.com", &a_public).is_err());
+        }
     }
 
-}
\ No newline at end of file
+}
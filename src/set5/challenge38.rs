@@ -1,14 +1,189 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
+use crate::bignum::{modexp, random_below, BigUint};
+use crate::error::Error;
+use crate::set2::challenge11::random_bytes;
+use crate::set5::challenge36::{password_exponent, srp_proof, srp_session_key, SrpParams, SRP_SALT_LEN};
+
+
+// Server's answer in simplified SRP: the salt, B = g^b and a random 128-bit u. B does not depend on the password.
+#[derive(Debug, Clone)]
+pub struct SimplifiedSrpChallenge {
+    pub salt: Vec<u8>,
+    pub b_public: BigUint,
+    pub u: BigUint,
+}
+
+pub trait SimplifiedSrpServer {
+    fn start_login(&mut self, email: &str, a_public: &BigUint) -> Result<SimplifiedSrpChallenge, Error>;
+    fn finish_login(&mut self, email: &str, proof: &[u8]) -> Result<bool, Error>;
+}
+
+// Honest server for a single user.
+pub struct HonestSimplifiedServer {
+    params: SrpParams,
+    email: String,
+    salt: Vec<u8>,
+    v: BigUint,
+    session: Option<(BigUint, BigUint, SimplifiedSrpChallenge)>,
+}
+
+impl HonestSimplifiedServer {
+    pub fn new(params: SrpParams, email: &str, password: &str) -> HonestSimplifiedServer {
+        let salt = random_bytes(SRP_SALT_LEN);
+        let v = modexp(&params.g, &password_exponent(&salt, password), &params.n);
+        HonestSimplifiedServer { params, email: email.to_owned(), salt, v, session: None }
+    }
+}
+
+impl SimplifiedSrpServer for HonestSimplifiedServer {
+    fn start_login(&mut self, email: &str, a_public: &BigUint) -> Result<SimplifiedSrpChallenge, Error> {
+        if email != self.email {
+            return Err(Error::InvalidFormat(format!("unknown user {}", email)));
+        }
+
+        let b_private = random_below(&self.params.n);
+        let b_public = modexp(&self.params.g, &b_private, &self.params.n);
+        let u = random_below(&(BigUint::from(1u32) << 128));
+        let challenge = SimplifiedSrpChallenge { salt: self.salt.clone(), b_public, u };
+        self.session = Some((a_public.clone(), b_private, challenge.clone()));
+        Ok(challenge)
+    }
+
+    // S = (A * v^u)^b
+    fn finish_login(&mut self, _email: &str, proof: &[u8]) -> Result<bool, Error> {
+        let (a_public, b_private, challenge) = self.session.take()
+            .ok_or_else(|| Error::InvalidFormat("no login in progress".to_owned()))?;
+        let n = &self.params.n;
+
+        let shared_secret = modexp(&(a_public * modexp(&self.v, &challenge.u, n)), &b_private, n);
+        Ok(srp_proof(&srp_session_key(&shared_secret), &self.salt) == proof)
+    }
+}
+
+// Client side of simplified SRP, S = B^(a + u * x).
+pub fn simplified_srp_login(params: &SrpParams, server: &mut impl SimplifiedSrpServer, email: &str, password: &str) -> Result<bool, Error> {
+    let a_private = random_below(&params.n);
+    let a_public = modexp(&params.g, &a_private, &params.n);
+
+    let challenge = server.start_login(email, &a_public)?;
+    let x = password_exponent(&challenge.salt, password);
+    let shared_secret = modexp(&challenge.b_public, &(a_private + &challenge.u * x), &params.n);
+    server.finish_login(email, &srp_proof(&srp_session_key(&shared_secret), &challenge.salt))
+}
+
+// Everything a man in the middle needs to test password guesses offline.
+#[derive(Debug, Clone)]
+pub struct CapturedLogin {
+    pub salt: Vec<u8>,
+    pub a_public: BigUint,
+    pub proof: Vec<u8>,
+}
+
+// Server impersonator that picks b = 1, u = 1 and an empty salt, so S = A * g^x and the proof only depends on
+// the password once A is known.
+pub struct MitmSimplifiedServer {
+    params: SrpParams,
+    a_public: Option<BigUint>,
+    pub captured: Option<CapturedLogin>,
+}
+
+impl MitmSimplifiedServer {
+    pub fn new(params: SrpParams) -> MitmSimplifiedServer {
+        MitmSimplifiedServer { params, a_public: None, captured: None }
+    }
+}
+
+impl SimplifiedSrpServer for MitmSimplifiedServer {
+    fn start_login(&mut self, _email: &str, a_public: &BigUint) -> Result<SimplifiedSrpChallenge, Error> {
+        self.a_public = Some(a_public.clone());
+        Ok(SimplifiedSrpChallenge { salt: Vec::new(), b_public: self.params.g.clone(), u: BigUint::from(1u32) })
+    }
+
+    // The login is refused, the proof has already been captured.
+    fn finish_login(&mut self, _email: &str, proof: &[u8]) -> Result<bool, Error> {
+        let a_public = self.a_public.take().ok_or_else(|| Error::InvalidFormat("no login in progress".to_owned()))?;
+        self.captured = Some(CapturedLogin { salt: Vec::new(), a_public, proof: proof.to_vec() });
+        Ok(false)
+    }
+}
+
+fn is_password(params: &SrpParams, captured: &CapturedLogin, candidate: &str) -> bool {
+    let x = password_exponent(&captured.salt, candidate);
+    let shared_secret = (&captured.a_public * modexp(&params.g, &x, &params.n)) % &params.n;
+    srp_proof(&srp_session_key(&shared_secret), &captured.salt) == captured.proof
+}
+
+// Try every word of the list, one word per line, splitting the list across the available cores.
+pub fn crack_simplified_srp(params: &SrpParams, captured: &CapturedLogin, wordlist: &Path) -> Result<Option<String>, Error> {
+    let contents = fs::read_to_string(wordlist)?;
+    let words: Vec<&str> = contents.lines().map(str::trim).filter(|word| !word.is_empty()).collect();
+    if words.is_empty() {
+        return Ok(None);
+    }
+
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = words.len().div_ceil(threads);
+    let found = AtomicBool::new(false);
+
+    let password = thread::scope(|scope| {
+        let workers: Vec<_> = words.chunks(chunk_size).map(|chunk| {
+            let found = &found;
+            scope.spawn(move || {
+                chunk.iter()
+                    .take_while(|_| !found.load(Ordering::Relaxed))
+                    .find(|word| is_password(params, captured, word))
+                    .inspect(|_| found.store(true, Ordering::Relaxed))
+            })
+        }).collect();
+
+        workers.into_iter().find_map(|worker| worker.join().unwrap())
+    });
+
+    Ok(password.map(|word| word.to_string()))
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use std::env;
+    use super::*;
+
+    const WORDLIST: &str = "123456\npassword\nqwerty\nletmein\ndragon\nmonkey\nsunshine\nprincess\nfootball\n\
+                            shadow\nmaster\nbaseball\nwelcome\nsubmarine\ntrustno1\niloveyou\nstarwars\nhunter2\n";
+
+    #[test]
+    fn test_simplified_srp() {
+        let params = SrpParams::default();
+        let mut server = HonestSimplifiedServer::new(params.clone(), "alice@example.com", "submarine");
+
+        assert_eq!(Ok(true), simplified_srp_login(&params, &mut server, "alice@example.com", "submarine"));
+        assert_eq!(Ok(false), simplified_srp_login(&params, &mut server, "alice@example.com", "dragon"));
+        assert!(simplified_srp_login(&params, &mut server, "bob@example.com", "submarine").is_err());
+    }
 
     #[test]
     fn run_challenge38() {
+        let params = SrpParams::default();
+        let wordlist = env::temp_dir().join(format!("challenge38-wordlist-{}.txt", std::process::id()));
+        fs::write(&wordlist, WORDLIST).unwrap();
+
+        let mut mallory = MitmSimplifiedServer::new(params.clone());
+        assert_eq!(Ok(false), simplified_srp_login(&params, &mut mallory, "alice@example.com", "hunter2"));
+        let captured = mallory.captured.unwrap();
+
+        let cracked = crack_simplified_srp(&params, &captured, &wordlist);
+        let missing = crack_simplified_srp(&params, &CapturedLogin { proof: vec![0; 32], ..captured }, &wordlist);
+        fs::remove_file(&wordlist).unwrap();
 
+        assert_eq!(Ok(Some("hunter2".to_owned())), cracked);
+        assert_eq!(Ok(None), missing);
+        assert!(crack_simplified_srp(&params, &CapturedLogin { salt: vec![], a_public: BigUint::from(2u32), proof: vec![] },
+                                     Path::new("/nonexistent/wordlist.txt")).is_err());
     }
 
-}
\ No newline at end of file
+}
@@ -1,14 +1,44 @@
+use crate::bignum::{crt, nth_root, BigUint};
+use crate::error::Error;
 
 
+// The same message encrypted with e = 3 under three moduli: CRT gives m^3 modulo n1 * n2 * n3, and since m is
+// below every modulus m^3 is below their product, so the integer cube root is m.
+pub fn e3_broadcast_attack(pairs: &[(BigUint, BigUint); 3]) -> Result<BigUint, Error> {
+    let congruences: Vec<(BigUint, BigUint)> = pairs.iter().map(|(n, c)| (c.clone(), n.clone())).collect();
+    let (cube, _) = crt(&congruences)?;
+
+    let message = nth_root(&cube, 3);
+    if message.pow(3) != cube {
+        return Err(Error::AttackFailed("combined ciphertext is not a perfect cube".to_owned()));
+    }
+    Ok(message)
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge39::RsaPrivateKey;
+    use super::*;
 
     #[test]
     fn run_challenge40() {
+        let message = BigUint::from_bytes_be(b"Broadcast to three recipients with e = 3");
+
+        let keys: Vec<RsaPrivateKey> = (0..3).map(|_| RsaPrivateKey::generate(512, 3).unwrap()).collect();
+        let pairs: [(BigUint, BigUint); 3] = keys.iter()
+            .map(|key| (key.n.clone(), key.public_key().encrypt(&message).unwrap()))
+            .collect::<Vec<_>>().try_into().unwrap();
 
+        assert_eq!(Ok(message), e3_broadcast_attack(&pairs));
+    }
+
+    #[test]
+    fn test_e3_broadcast_attack_needs_coprime_moduli() {
+        let n = BigUint::from(3233u32);
+        let pairs = [(n.clone(), BigUint::from(1u32)), (n.clone(), BigUint::from(1u32)), (n, BigUint::from(1u32))];
+        assert!(e3_broadcast_attack(&pairs).is_err());
     }
 
-}
\ No newline at end of file
+}
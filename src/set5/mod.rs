@@ -1,8 +1,8 @@
-mod challenge33;
-mod challenge34;
-mod challenge35;
-mod challenge36;
-mod challenge37;
-mod challenge38;
-mod challenge39;
-mod challenge40;
\ No newline at end of file
+pub mod challenge33;
+pub mod challenge34;
+pub mod challenge35;
+pub mod challenge36;
+pub mod challenge37;
+pub mod challenge38;
+pub mod challenge39;
+pub mod challenge40;
\ No newline at end of file
@@ -1,14 +1,116 @@
+use std::collections::HashSet;
 
+use crate::bignum::{modexp, modinv, random_range, BigUint};
+use crate::error::Error;
+use crate::set4::challenge28::HashFunction;
+use crate::set4::challenge30::Sha256;
+use crate::set5::challenge39::{RsaPrivateKey, RsaPublicKey};
+
+
+// Random r coprime to n, with its inverse.
+fn blinding_factor(n: &BigUint) -> (BigUint, BigUint) {
+    loop {
+        let r = random_range(&BigUint::from(2u32), n);
+        if let Some(r_inverse) = modinv(&r, n) {
+            return (r, r_inverse);
+        }
+    }
+}
+
+impl RsaPrivateKey {
+    // Private operation on c * r^e for a fresh random r, unblinded with r^-1 afterwards. The exponentiation never
+    // sees the caller's ciphertext, so its timing cannot be correlated with it.
+    pub fn decrypt_blinded(&self, ciphertext: &BigUint) -> Result<BigUint, Error> {
+        // checked before blinding, which would otherwise reduce an out of range ciphertext silently
+        if *ciphertext >= self.n {
+            return Err(Error::InvalidFormat("ciphertext is not smaller than the modulus".to_owned()));
+        }
+
+        let (r, r_inverse) = blinding_factor(&self.n);
+        let blinded = (ciphertext * modexp(&r, &self.e, &self.n)) % &self.n;
+        Ok((self.decrypt(&blinded)? * r_inverse) % &self.n)
+    }
+}
+
+// Decrypts any ciphertext once, remembering the hash of every ciphertext it has seen to refuse replays.
+pub struct RsaDecryptionServer {
+    key: RsaPrivateKey,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl RsaDecryptionServer {
+    pub fn new(key: RsaPrivateKey) -> RsaDecryptionServer {
+        RsaDecryptionServer { key, seen: HashSet::new() }
+    }
+
+    pub fn public_key(&self) -> RsaPublicKey {
+        self.key.public_key()
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &BigUint) -> Result<BigUint, Error> {
+        // only ciphertexts that decrypted are remembered, a rejected one can be submitted again
+        let hash = Sha256::digest(&ciphertext.to_bytes_be());
+        if self.seen.contains(&hash) {
+            return Err(Error::Replayed);
+        }
+        let plaintext = self.key.decrypt_blinded(ciphertext)?;
+        self.seen.insert(hash);
+        Ok(plaintext)
+    }
+}
+
+// Submit c' = s^e * c instead of c, which the server has never seen. It decrypts to s * m, and dividing by s
+// recovers m.
+pub fn unpadded_message_recovery(
+    public_key: &RsaPublicKey,
+    ciphertext: &BigUint,
+    mut oracle: impl FnMut(&BigUint) -> Result<BigUint, Error>,
+) -> Result<BigUint, Error> {
+    let n = &public_key.n;
+    // s is drawn from [2, n), which rules out s = 1 resubmitting the original ciphertext
+    let (s, s_inverse) = blinding_factor(n);
+
+    let disguised = (modexp(&s, &public_key.e, n) * ciphertext) % n;
+    let blinded_plaintext = oracle(&disguised)?;
+    Ok((blinded_plaintext * s_inverse) % n)
+}
 
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge39::RSA_DEFAULT_EXPONENT;
+    use super::*;
+
+    #[test]
+    fn test_decrypt_blinded() {
+        let key = RsaPrivateKey::generate(512, RSA_DEFAULT_EXPONENT).unwrap();
+        let message = BigUint::from_bytes_be(b"blinded private operation");
+        let ciphertext = key.public_key().encrypt(&message).unwrap();
+
+        assert_eq!(Ok(message.clone()), key.decrypt_blinded(&ciphertext));
+        assert_eq!(Ok(message), key.decrypt(&ciphertext));
+    }
 
     #[test]
     fn run_challenge41() {
+        let mut server = RsaDecryptionServer::new(RsaPrivateKey::generate(1024, RSA_DEFAULT_EXPONENT).unwrap());
+        let public_key = server.public_key();
+
+        let message = BigUint::from_bytes_be(b"{time: 1356304276, social: '555-55-5555'}");
+        let ciphertext = public_key.encrypt(&message).unwrap();
+
+        // the victim's own request goes through, after which the server refuses the same ciphertext
+        assert_eq!(Ok(message.clone()), server.decrypt(&ciphertext));
+        assert_eq!(Err(Error::Replayed), server.decrypt(&ciphertext));
+
+        // a ciphertext that fails to decrypt is not remembered
+        let out_of_range = &public_key.n + 1u32;
+        assert!(matches!(server.decrypt(&out_of_range), Err(Error::InvalidFormat(_))));
+        assert!(matches!(server.decrypt(&out_of_range), Err(Error::InvalidFormat(_))));
 
+        let recovered = unpadded_message_recovery(&public_key, &ciphertext, |c| server.decrypt(c));
+        assert_eq!(Ok(message), recovered);
     }
 
-}
\ No newline at end of file
+}
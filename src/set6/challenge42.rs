@@ -1,14 +1,184 @@
+use crate::bignum::{nth_root, to_bytes_be, BigUint};
+use crate::error::Error;
+use crate::set4::challenge28::{HashFunction, Sha1};
+use crate::set4::challenge30::Sha256;
+use crate::set5::challenge39::{RsaPrivateKey, RsaPublicKey};
 
 
+// DER encoded DigestInfo headers from RFC 8017, the hash value follows directly.
+const SHA1_DIGEST_INFO: [u8; 15] = [0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14];
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+
+// PKCS#1 v1.5 requires at least 8 bytes of 0xff padding.
+const MIN_PADDING_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureHash {
+    Sha1,
+    Sha256,
+}
+
+impl SignatureHash {
+    pub fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            SignatureHash::Sha1 => &SHA1_DIGEST_INFO,
+            SignatureHash::Sha256 => &SHA256_DIGEST_INFO,
+        }
+    }
+
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            SignatureHash::Sha1 => Sha1::digest(message),
+            SignatureHash::Sha256 => Sha256::digest(message),
+        }
+    }
+
+    // DigestInfo of the message, the part of the encoded block after the padding.
+    pub fn digest_info(&self, message: &[u8]) -> Vec<u8> {
+        [self.digest_info_prefix(), &self.digest(message)].concat()
+    }
+}
+
+// How a signature is checked once it has been raised to e.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verifier {
+    // Rebuild the whole expected block and compare, so nothing can follow the hash.
+    Strict,
+    // Walk 00 01 ff .. ff 00 then read DigestInfo and the hash, ignoring whatever comes after, as the
+    // vulnerable implementations of the challenge did.
+    Broken,
+}
+
+// EMSA-PKCS1-v1_5: 00 01 ff .. ff 00 DigestInfo, exactly the size of the modulus.
+pub fn pkcs1_v15_encode(hash: SignatureHash, message: &[u8], len: usize) -> Result<Vec<u8>, Error> {
+    let digest_info = hash.digest_info(message);
+    if len < digest_info.len() + 3 + MIN_PADDING_LEN {
+        return Err(Error::InvalidFormat(format!("{} byte modulus is too short for the digest", len)));
+    }
+    let padding = vec![0xff; len - digest_info.len() - 3];
+    Ok([&[0x00, 0x01][..], &padding, &[0x00], &digest_info].concat())
+}
+
+pub fn pkcs1_v15_sign(key: &RsaPrivateKey, hash: SignatureHash, message: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = key.public_key().size_in_bytes();
+    let encoded = pkcs1_v15_encode(hash, message, len)?;
+    let signature = key.decrypt(&BigUint::from_bytes_be(&encoded))?;
+    to_bytes_be(&signature, len)
+}
+
+fn broken_check(encoded: &[u8], hash: SignatureHash, message: &[u8]) -> bool {
+    let Some(rest) = encoded.strip_prefix(&[0x00, 0x01]) else {
+        return false;
+    };
+    let padding_len = rest.iter().take_while(|&&b| b == 0xff).count();
+    if padding_len == 0 {
+        return false;
+    }
+    let Some(rest) = rest[padding_len..].strip_prefix(&[0x00]) else {
+        return false;
+    };
+    rest.starts_with(&hash.digest_info(message))
+}
+
+pub fn pkcs1_v15_verify(key: &RsaPublicKey, hash: SignatureHash, message: &[u8], signature: &[u8], verifier: Verifier) -> bool {
+    let len = key.size_in_bytes();
+    // RFC 8017 8.2.2 step 1, the signature must be exactly as long as the modulus
+    if verifier == Verifier::Strict && signature.len() != len {
+        return false;
+    }
+
+    let signature = BigUint::from_bytes_be(signature);
+    let Ok(encoded) = key.encrypt(&signature).and_then(|m| to_bytes_be(&m, len)) else {
+        return false;
+    };
+
+    match verifier {
+        Verifier::Strict => pkcs1_v15_encode(hash, message, len).is_ok_and(|expected| expected == encoded),
+        Verifier::Broken => broken_check(&encoded, hash, message),
+    }
+}
+
+// Bleichenbacher's e = 3 forgery: 00 01 ff 00 DigestInfo followed by garbage. Any cube between the block with
+// all-zero and all-one garbage passes the broken verifier, and with enough garbage such a cube always exists.
+pub fn forge_e3_signature(key: &RsaPublicKey, hash: SignatureHash, message: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.e != BigUint::from(3u32) {
+        return Err(Error::AttackFailed("cube root forgery needs e = 3".to_owned()));
+    }
+
+    let len = key.size_in_bytes();
+    let prefix = [&[0x00, 0x01, 0xff, 0x00][..], &hash.digest_info(message)].concat();
+    if prefix.len() >= len {
+        return Err(Error::AttackFailed("modulus is too short to leave room for garbage".to_owned()));
+    }
+    let garbage_len = len - prefix.len();
+    let low = BigUint::from_bytes_be(&[&prefix[..], &vec![0x00; garbage_len]].concat());
+    let high = BigUint::from_bytes_be(&[&prefix[..], &vec![0xff; garbage_len]].concat());
+
+    let root = nth_root(&high, 3);
+    if root.pow(3) < low {
+        return Err(Error::AttackFailed("no cube between the bounds".to_owned()));
+    }
+    to_bytes_be(&root, len)
+}
+
 
 #[cfg(test)]
 mod tests {
 
-    //use super::*;
+    use crate::set5::challenge39::RSA_DEFAULT_EXPONENT;
+    use super::*;
+
+    const MESSAGE: &[u8] = b"hi mom";
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = RsaPrivateKey::generate(1024, RSA_DEFAULT_EXPONENT).unwrap();
+        let public = key.public_key();
+
+        for hash in [SignatureHash::Sha1, SignatureHash::Sha256] {
+            let signature = pkcs1_v15_sign(&key, hash, MESSAGE).unwrap();
+            assert_eq!(128, signature.len());
+
+            for verifier in [Verifier::Strict, Verifier::Broken] {
+                assert!(pkcs1_v15_verify(&public, hash, MESSAGE, &signature, verifier));
+                assert!(!pkcs1_v15_verify(&public, hash, b"hi dad", &signature, verifier));
+            }
+
+            // the same integer with a leading zero byte is not a valid encoding of the signature
+            let padded = [&[0u8][..], &signature].concat();
+            assert!(!pkcs1_v15_verify(&public, hash, MESSAGE, &padded, Verifier::Strict));
+        }
+
+        let sha1_signature = pkcs1_v15_sign(&key, SignatureHash::Sha1, MESSAGE).unwrap();
+        assert!(!pkcs1_v15_verify(&public, SignatureHash::Sha256, MESSAGE, &sha1_signature, Verifier::Strict));
+        assert!(pkcs1_v15_sign(&RsaPrivateKey::generate(256, RSA_DEFAULT_EXPONENT).unwrap(), SignatureHash::Sha256, MESSAGE).is_err());
+    }
+
+    #[test]
+    fn test_encoding() {
+        let encoded = pkcs1_v15_encode(SignatureHash::Sha1, b"abc", 64).unwrap();
+        assert_eq!(64, encoded.len());
+        assert_eq!(&[0x00, 0x01, 0xff], &encoded[..3]);
+        assert_eq!("3021300906052b0e03021a05000414a9993e364706816aba3e25717850c26c9cd0d89d", hex::encode(&encoded[29..]));
+    }
 
     #[test]
     fn run_challenge42() {
+        // the garbage has to span the gap between consecutive cubes, which for SHA-256 takes a 2048-bit modulus
+        for (bits, hash) in [(1024, SignatureHash::Sha1), (2048, SignatureHash::Sha256)] {
+            let public = RsaPrivateKey::generate(bits, 3).unwrap().public_key();
+            let forged = forge_e3_signature(&public, hash, MESSAGE).unwrap();
+
+            assert!(pkcs1_v15_verify(&public, hash, MESSAGE, &forged, Verifier::Broken));
+            assert!(!pkcs1_v15_verify(&public, hash, MESSAGE, &forged, Verifier::Strict));
+        }
 
+        let short = RsaPrivateKey::generate(1024, 3).unwrap().public_key();
+        assert!(forge_e3_signature(&short, SignatureHash::Sha256, MESSAGE).is_err());
+        let e65537 = RsaPrivateKey::generate(512, RSA_DEFAULT_EXPONENT).unwrap().public_key();
+        assert!(forge_e3_signature(&e65537, SignatureHash::Sha1, MESSAGE).is_err());
     }
 
-}
\ No newline at end of file
+}
@@ -1,8 +1,8 @@
-mod challenge41;
-mod challenge42;
-mod challenge43;
-mod challenge44;
-mod challenge45;
-mod challenge46;
-mod challenge47;
-mod challenge48;
+pub mod challenge41;
+pub mod challenge42;
+pub mod challenge43;
+pub mod challenge44;
+pub mod challenge45;
+pub mod challenge46;
+pub mod challenge47;
+pub mod challenge48;